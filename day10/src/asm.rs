@@ -0,0 +1,381 @@
+//! Writing programs that draw a given picture on the CRT, and listing what
+//! a program does cycle by cycle.
+//!
+//! X can only change when an `addx` finishes, which takes two cycles, so it
+//! keeps every value for at least two pixels. That's no real limit, some X
+//! lights any two pixels side by side just the way they should be. But X
+//! starts at 1, which lights the first two pixels whatever the program, so
+//! pictures without them can't be drawn.
+
+use crate::{instructions::Instruction, Cpu, Tick, CRT_COLUMNS, CRT_ROWS};
+use std::fmt::{Display, Write};
+
+/// which pixels of the screen are lit, row by row from the top
+pub type Bitmap = [bool; CRT_COLUMNS * CRT_ROWS];
+
+#[derive(Debug, PartialEq)]
+pub enum AssembleError {
+    TooBig {
+        rows: usize,
+        columns: usize,
+    },
+    /// no way of getting X right for this pixel given the ones before it,
+    /// which only happens to the first two
+    Undrawable {
+        row: usize,
+        column: usize,
+    },
+    UnknownLetter(char),
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssembleError::TooBig { rows, columns } => write!(
+                f,
+                "the picture is {}x{}, the screen only {}x{}",
+                columns, rows, CRT_COLUMNS, CRT_ROWS
+            ),
+            AssembleError::Undrawable { row, column } => write!(
+                f,
+                "row {} column {} can't be dark, X starts at 1 and lights it",
+                row, column
+            ),
+            AssembleError::UnknownLetter(letter) => {
+                write!(f, "{:?} isn't in the font", letter)
+            }
+        }
+    }
+}
+
+/// The puzzle's letters, four pixels wide with a blank column after each,
+/// so eight of them fill the screen.
+const FONT: [(char, [&str; CRT_ROWS]); 19] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ('.', ["....", "....", "....", "....", "....", "...."]),
+    (' ', ["....", "....", "....", "....", "....", "...."]),
+];
+
+/// Up to `CRT_ROWS` lines of up to `CRT_COLUMNS` pixels, `#` being lit
+/// and anything else dark. Whatever's missing is dark.
+pub fn parse_bitmap(picture: &str) -> Result<Bitmap, AssembleError> {
+    let lines: Vec<&str> = picture.lines().collect();
+    let columns = lines.iter().map(|line| line.chars().count()).max();
+    if lines.len() > CRT_ROWS || columns.unwrap_or(0) > CRT_COLUMNS {
+        return Err(AssembleError::TooBig {
+            rows: lines.len(),
+            columns: columns.unwrap_or(0),
+        });
+    }
+
+    let mut bitmap = [false; CRT_COLUMNS * CRT_ROWS];
+    for (y, line) in lines.iter().enumerate() {
+        for (x, pixel) in line.chars().enumerate() {
+            bitmap[y * CRT_COLUMNS + x] = pixel == '#';
+        }
+    }
+    Ok(bitmap)
+}
+
+/// `text` written in the puzzle's font, upper case letters and spaces.
+/// Unless the first letter lights the top two pixels on the left, the
+/// text moves two columns right and they're lit in the margin, as X
+/// lights them anyway.
+pub fn text_bitmap(text: &str) -> Result<Bitmap, AssembleError> {
+    let mut rows = vec![String::new(); CRT_ROWS];
+    for letter in text.chars() {
+        let (_, glyph) = FONT
+            .iter()
+            .find(|(c, _)| *c == letter)
+            .ok_or(AssembleError::UnknownLetter(letter))?;
+        for (row, glyph_row) in rows.iter_mut().zip(glyph) {
+            row.push_str(glyph_row);
+            row.push('.');
+        }
+    }
+
+    if !rows[0].starts_with("##") {
+        for (y, row) in rows.iter_mut().enumerate() {
+            row.insert_str(0, if y == 0 { "##" } else { ".." });
+        }
+    }
+    // the blank after the last letter may fall off the screen
+    let columns = rows[0].len();
+    if columns - 1 > CRT_COLUMNS {
+        return Err(AssembleError::TooBig {
+            rows: CRT_ROWS,
+            columns,
+        });
+    }
+    for row in rows.iter_mut() {
+        row.truncate(CRT_COLUMNS);
+    }
+    parse_bitmap(&rows.join("\n"))
+}
+
+/// values of X that make a difference, beyond them nothing is lit
+const LOWEST: i32 = -2;
+const HIGHEST: i32 = CRT_COLUMNS as i32 + 1;
+
+/// Finds X for every pixel so the CRT draws `bitmap`, keeping each value
+/// for at least two pixels and changing it as few times as it can, then
+/// writes that down as `addx` and `noop`.
+pub fn assemble(bitmap: &Bitmap) -> Result<Vec<Instruction>, AssembleError> {
+    let values = (HIGHEST - LOWEST + 1) as usize;
+    let lit = |pixel: usize, x: i32| ((pixel % CRT_COLUMNS) as i32).abs_diff(x) <= 1;
+    // per pixel and value of X, whether it has been held for a single
+    // pixel or more: the fewest changes to get there and where from
+    let state = |x: i32, held: usize| (x - LOWEST) as usize * 2 + held;
+    let mut costs = vec![vec![None::<(u32, usize)>; values * 2]; bitmap.len()];
+
+    if lit(0, 1) != bitmap[0] {
+        return Err(AssembleError::Undrawable { row: 0, column: 0 });
+    }
+    costs[0][state(1, 0)] = Some((0, usize::MAX));
+    for pixel in 1..bitmap.len() {
+        let (before, now) = costs.split_at_mut(pixel);
+        let (before, now) = (&before[pixel - 1], &mut now[0]);
+        for x in LOWEST..=HIGHEST {
+            if lit(pixel, x) != bitmap[pixel] {
+                continue;
+            }
+            let mut best = |held: usize, from: usize, cost: u32| {
+                let better = match now[state(x, held)] {
+                    Some((old, _)) => cost < old,
+                    None => true,
+                };
+                if better {
+                    now[state(x, held)] = Some((cost, from));
+                }
+            };
+
+            // keeping X
+            for held in 0..2 {
+                if let Some((cost, _)) = before[state(x, held)] {
+                    best(1, state(x, held), cost);
+                }
+            }
+            // changing it, if it's been held long enough
+            for from in LOWEST..=HIGHEST {
+                if from == x {
+                    continue;
+                }
+                if let Some((cost, _)) = before[state(from, 1)] {
+                    best(0, state(from, 1), cost + 1);
+                }
+            }
+        }
+
+        if now.iter().all(Option::is_none) {
+            return Err(AssembleError::Undrawable {
+                row: pixel / CRT_COLUMNS,
+                column: pixel % CRT_COLUMNS,
+            });
+        }
+    }
+
+    // back from the cheapest end to the value of X at every pixel
+    let mut at = (0..values * 2)
+        .filter(|&s| costs[bitmap.len() - 1][s].is_some())
+        .min_by_key(|&s| costs[bitmap.len() - 1][s].unwrap().0)
+        .unwrap();
+    let mut xs = vec![0; bitmap.len()];
+    for pixel in (0..bitmap.len()).rev() {
+        xs[pixel] = (at / 2) as i32 + LOWEST;
+        at = costs[pixel][at].unwrap().1;
+    }
+
+    // a value held for n pixels is n - 2 noops and the addx to the next
+    let mut program = vec![];
+    let mut start = 0;
+    for pixel in 1..=xs.len() {
+        if pixel < xs.len() && xs[pixel] == xs[start] {
+            continue;
+        }
+        if pixel == xs.len() {
+            program.extend(vec![Instruction::new("noop", &[]); pixel - start]);
+        } else {
+            program.extend(vec![Instruction::new("noop", &[]); pixel - start - 2]);
+            program.push(Instruction::new("addx", &[xs[pixel] - xs[start]]));
+        }
+        start = pixel;
+    }
+    Ok(program)
+}
+
+/// One line per instruction run in the first `cycles` cycles, with the
+/// cycles it took, where it is in the program and what X was, like
+/// `  2-3     1  addx 3        x 1 -> 4`.
+pub fn listing(program: &[Instruction], cycles: usize) -> String {
+    let ticks: Vec<Tick> = Cpu::new(program.to_vec()).take(cycles).collect();
+    let mut out = String::new();
+
+    let mut i = 0;
+    while i < ticks.len() {
+        let instruction = program[ticks[i].pc];
+        let run = &ticks[i..(i + instruction.cycles() as usize).min(ticks.len())];
+        let (first, last) = (run[0], run[run.len() - 1]);
+
+        let cycles = if first.cycle == last.cycle {
+            first.cycle.to_string()
+        } else {
+            format!("{}-{}", first.cycle, last.cycle)
+        };
+        let x = if first.during == last.after {
+            format!("x {}", first.during)
+        } else {
+            format!("x {} -> {}", first.during, last.after)
+        };
+        writeln!(
+            out,
+            "{:>7} {:>5}  {:<12}  {}",
+            cycles,
+            first.pc,
+            instruction.to_string(),
+            x
+        )
+        .unwrap();
+        i += run.len();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble, listing, parse_bitmap, text_bitmap, AssembleError};
+    use crate::{draw, parse_line, solve2, CRT_COLUMNS, CRT_ROWS};
+
+    #[test]
+    fn test_round_trip() {
+        let picture = solve2("input.txt");
+        let bitmap = parse_bitmap(&picture).unwrap();
+        assert_eq!(draw(assemble(&bitmap).unwrap()), picture);
+
+        // the picture is the puzzle's answer
+        assert_eq!(text_bitmap("PLULKBZH").unwrap(), bitmap);
+    }
+
+    #[test]
+    fn test_text_without_the_first_pixels() {
+        // H is dark at the top second from the left, I at the top left
+        for text in ["HI", "I", " ", "AC"] {
+            let bitmap = text_bitmap(text).unwrap();
+            assert!(bitmap[0] && bitmap[1], "{:?}", text);
+            let program = assemble(&bitmap).unwrap();
+            assert_eq!(parse_bitmap(&draw(program)).unwrap(), bitmap);
+        }
+        // H starts after the margin, which is dark below the top row
+        let bitmap = text_bitmap("HI").unwrap();
+        assert!(bitmap[2] && !bitmap[3] && !bitmap[CRT_COLUMNS]);
+    }
+
+    #[test]
+    fn test_test_input_picture() {
+        let picture = solve2("test_input.txt");
+        let program = assemble(&parse_bitmap(&picture).unwrap()).unwrap();
+        assert_eq!(draw(program.clone()), picture);
+
+        // 240 cycles, addx taking two
+        let cycles: u32 = program.iter().map(|i| i.cycles()).sum();
+        assert_eq!(cycles, 240);
+    }
+
+    #[test]
+    fn test_undrawable() {
+        // X is 1 for the first two pixels
+        assert_eq!(
+            assemble(&parse_bitmap(".").unwrap()),
+            Err(AssembleError::Undrawable { row: 0, column: 0 })
+        );
+        assert_eq!(
+            assemble(&parse_bitmap("#.").unwrap()),
+            Err(AssembleError::Undrawable { row: 0, column: 1 })
+        );
+    }
+
+    #[test]
+    fn test_any_picture() {
+        // xorshift64
+        let mut state = 2022u64;
+        for _ in 0..20 {
+            let picture: Vec<String> = (0..CRT_ROWS)
+                .map(|row| {
+                    (0..CRT_COLUMNS)
+                        .map(|column| {
+                            state ^= state << 13;
+                            state ^= state >> 7;
+                            state ^= state << 17;
+                            let lit = (row == 0 && column < 2) || state & 1 == 0;
+                            if lit {
+                                '#'
+                            } else {
+                                ' '
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            let picture = picture.join("\n");
+
+            let program = assemble(&parse_bitmap(&picture).unwrap()).unwrap();
+            assert_eq!(draw(program), picture);
+        }
+    }
+
+    #[test]
+    fn test_bitmap_errors() {
+        assert_eq!(
+            text_bitmap("PQ").unwrap_err().to_string(),
+            "'Q' isn't in the font"
+        );
+        assert_eq!(
+            text_bitmap("PLULKBZHA").unwrap_err(),
+            AssembleError::TooBig {
+                rows: 6,
+                columns: 45
+            }
+        );
+        // seven letters and the margin
+        assert!(text_bitmap("HIJKLOP").is_ok());
+        assert_eq!(
+            text_bitmap("HIJKLOPR").unwrap_err(),
+            AssembleError::TooBig {
+                rows: 6,
+                columns: 42
+            }
+        );
+        assert!(parse_bitmap(&"#".repeat(41)).is_err());
+        assert!(parse_bitmap("\n\n\n\n\n\n#").is_err());
+    }
+
+    #[test]
+    fn test_listing() {
+        let program: Vec<_> = ["noop", "addx 3", "jnz x -1", "addx -5"]
+            .iter()
+            .map(|line| parse_line(line).unwrap())
+            .collect();
+        let expected = [
+            "      1     0  noop          x 1",
+            "    2-3     1  addx 3        x 1 -> 4",
+            "      4     2  jnz x -1      x 4",
+            "    5-6     1  addx 3        x 4 -> 7",
+        ];
+        assert_eq!(listing(&program, 6), expected.join("\n") + "\n");
+    }
+}
@@ -0,0 +1,322 @@
+//! The instruction set, one table entry per instruction.
+//!
+//! An entry says how the instruction is written, what its operands are,
+//! how many cycles it takes and what it does once they're over. Adding an
+//! instruction is adding an entry.
+
+use std::fmt::Display;
+
+/// x is the one the puzzle looks at
+pub const REGISTERS: [&str; 2] = ["x", "y"];
+pub const X: usize = 0;
+pub const Y: usize = 1;
+pub type Registers = [i32; REGISTERS.len()];
+
+const MAX_OPERANDS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperandKind {
+    /// one of `REGISTERS`, kept as its index
+    Register,
+    Value,
+    /// how many instructions to jump by, from the jump itself
+    Offset,
+}
+use OperandKind::{Offset, Register, Value};
+
+/// where to go once an instruction is over
+pub enum Flow {
+    Next,
+    Jump(i32),
+}
+
+pub struct Definition {
+    pub name: &'static str,
+    pub operands: &'static [OperandKind],
+    pub cycles: u32,
+    /// what it does with its operands once its cycles are over
+    pub execute: fn(&mut Registers, &[i32]) -> Flow,
+}
+
+/// a jump by `operands[1]` when register `operands[0]` passes `test`
+fn branch(registers: &Registers, operands: &[i32], test: fn(i32) -> bool) -> Flow {
+    if test(registers[operands[0] as usize]) {
+        Flow::Jump(operands[1])
+    } else {
+        Flow::Next
+    }
+}
+
+pub const INSTRUCTION_SET: &[Definition] = &[
+    Definition {
+        name: "noop",
+        operands: &[],
+        cycles: 1,
+        execute: |_, _| Flow::Next,
+    },
+    Definition {
+        name: "addx",
+        operands: &[Value],
+        cycles: 2,
+        execute: |registers, operands| {
+            registers[X] = registers[X].wrapping_add(operands[0]);
+            Flow::Next
+        },
+    },
+    Definition {
+        name: "addy",
+        operands: &[Value],
+        cycles: 2,
+        execute: |registers, operands| {
+            registers[Y] = registers[Y].wrapping_add(operands[0]);
+            Flow::Next
+        },
+    },
+    Definition {
+        name: "mulx",
+        operands: &[Value],
+        cycles: 3,
+        execute: |registers, operands| {
+            registers[X] = registers[X].wrapping_mul(operands[0]);
+            Flow::Next
+        },
+    },
+    Definition {
+        name: "jmp",
+        operands: &[Offset],
+        cycles: 1,
+        execute: |_, operands| Flow::Jump(operands[0]),
+    },
+    Definition {
+        name: "jnz",
+        operands: &[Register, Offset],
+        cycles: 1,
+        execute: |registers, operands| branch(registers, operands, |n| n != 0),
+    },
+    Definition {
+        name: "jz",
+        operands: &[Register, Offset],
+        cycles: 1,
+        execute: |registers, operands| branch(registers, operands, |n| n == 0),
+    },
+    Definition {
+        name: "jgz",
+        operands: &[Register, Offset],
+        cycles: 1,
+        execute: |registers, operands| branch(registers, operands, |n| n > 0),
+    },
+    Definition {
+        name: "jlz",
+        operands: &[Register, Offset],
+        cycles: 1,
+        execute: |registers, operands| branch(registers, operands, |n| n < 0),
+    },
+];
+
+/// An entry of `INSTRUCTION_SET` with its operands, registers by index.
+/// Operands past the ones it takes are 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    opcode: usize,
+    operands: [i32; MAX_OPERANDS],
+}
+
+impl Instruction {
+    /// Instruction `name` with `operands`, registers by index. Panics if
+    /// there's no such instruction or it takes another number of operands.
+    pub fn new(name: &str, operands: &[i32]) -> Instruction {
+        let opcode = INSTRUCTION_SET
+            .iter()
+            .position(|definition| definition.name == name)
+            .unwrap_or_else(|| panic!("{:?} is not an instruction", name));
+        assert_eq!(
+            operands.len(),
+            INSTRUCTION_SET[opcode].operands.len(),
+            "wrong number of operands for {}",
+            name
+        );
+
+        let mut instruction = Instruction {
+            opcode,
+            operands: [0; MAX_OPERANDS],
+        };
+        instruction.operands[..operands.len()].copy_from_slice(operands);
+        instruction
+    }
+
+    pub fn definition(&self) -> &'static Definition {
+        &INSTRUCTION_SET[self.opcode]
+    }
+
+    pub fn operands(&self) -> &[i32] {
+        &self.operands[..self.definition().operands.len()]
+    }
+
+    pub fn cycles(&self) -> u32 {
+        self.definition().cycles
+    }
+
+    pub fn execute(&self, registers: &mut Registers) -> Flow {
+        (self.definition().execute)(registers, self.operands())
+    }
+}
+
+/// the way `parse_line` reads it, offsets always signed
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let definition = self.definition();
+        write!(f, "{}", definition.name)?;
+        for (&operand, kind) in self.operands().iter().zip(definition.operands) {
+            match kind {
+                Register => write!(f, " {}", REGISTERS[operand as usize])?,
+                Value => write!(f, " {}", operand)?,
+                Offset => write!(f, " {:+}", operand)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    UnknownInstruction(String),
+    OperandCount {
+        name: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    InvalidOperand(String, OperandKind),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnknownInstruction(name) => write!(f, "{:?} is not an instruction", name),
+            ParseError::OperandCount {
+                name,
+                expected,
+                found,
+            } => write!(f, "{} takes {} operands, not {}", name, expected, found),
+            ParseError::InvalidOperand(operand, kind) => {
+                let kind = match kind {
+                    Register => format!("a register ({})", REGISTERS.join(", ")),
+                    Value => "a number".to_string(),
+                    Offset => "a jump offset".to_string(),
+                };
+                write!(f, "{:?} should be {}", operand, kind)
+            }
+        }
+    }
+}
+
+fn parse_operand(token: &str, kind: OperandKind) -> Result<i32, ParseError> {
+    let operand = match kind {
+        Register => REGISTERS
+            .iter()
+            .position(|&name| name == token)
+            .map(|i| i as i32),
+        Value | Offset => token.parse().ok(),
+    };
+    operand.ok_or(ParseError::InvalidOperand(token.to_string(), kind))
+}
+
+pub fn parse_line(line: &str) -> Result<Instruction, ParseError> {
+    let mut tokens = line.split_whitespace();
+    let name = tokens.next().unwrap_or("");
+    let operands: Vec<&str> = tokens.collect();
+
+    let opcode = INSTRUCTION_SET
+        .iter()
+        .position(|definition| definition.name == name)
+        .ok_or(ParseError::UnknownInstruction(name.to_string()))?;
+    let definition = &INSTRUCTION_SET[opcode];
+    if operands.len() != definition.operands.len() {
+        return Err(ParseError::OperandCount {
+            name: definition.name,
+            expected: definition.operands.len(),
+            found: operands.len(),
+        });
+    }
+
+    let mut instruction = Instruction {
+        opcode,
+        operands: [0; MAX_OPERANDS],
+    };
+    for (i, (token, &kind)) in operands.iter().zip(definition.operands).enumerate() {
+        instruction.operands[i] = parse_operand(token, kind)?;
+    }
+    Ok(instruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, Instruction, INSTRUCTION_SET, MAX_OPERANDS, Y};
+
+    #[test]
+    fn test_parse_line() {
+        let instruction = parse_line("jnz y -3").unwrap();
+        assert_eq!(instruction.definition().name, "jnz");
+        assert_eq!(instruction.operands(), [Y as i32, -3]);
+        assert_eq!(instruction.cycles(), 1);
+
+        let instruction = parse_line("  addx   +15 ").unwrap();
+        assert_eq!(instruction.operands(), [15]);
+        assert_eq!(instruction.cycles(), 2);
+        assert!(parse_line("noop").unwrap().operands().is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        for line in [
+            "noop",
+            "addx -7",
+            "mulx 3",
+            "jmp +0",
+            "jnz y -3",
+            "jlz x +12",
+        ] {
+            let instruction = parse_line(line).unwrap();
+            assert_eq!(instruction.to_string(), line);
+        }
+        assert_eq!(
+            Instruction::new("jz", &[Y as i32, 4]).to_string(),
+            "jz y +4"
+        );
+        assert_eq!(
+            Instruction::new("addx", &[5]),
+            parse_line("addx 5").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let tests = [
+            ("", "\"\" is not an instruction"),
+            ("addz 1", "\"addz\" is not an instruction"),
+            ("addx", "addx takes 1 operands, not 0"),
+            ("noop 3", "noop takes 0 operands, not 1"),
+            ("addx y", "\"y\" should be a number"),
+            ("jnz z 2", "\"z\" should be a register (x, y)"),
+            ("jmp far", "\"far\" should be a jump offset"),
+        ];
+
+        for (line, message) in tests {
+            assert_eq!(parse_line(line).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_table() {
+        for (i, definition) in INSTRUCTION_SET.iter().enumerate() {
+            assert!(definition.operands.len() <= MAX_OPERANDS);
+            assert!(
+                definition.cycles > 0,
+                "{} would take no time",
+                definition.name
+            );
+            assert!(INSTRUCTION_SET[..i]
+                .iter()
+                .all(|other| other.name != definition.name));
+        }
+    }
+}
@@ -1,94 +1,141 @@
 use std::{
+    env, fs,
     fs::File,
     io::{BufRead, BufReader},
+    process,
 };
 
-enum Instruction {
-    Add(i32),
-    Noop,
-}
-use Instruction::{Add, Noop};
-
-fn parse_line(line: &str) -> Instruction {
-    let tokens: Vec<&str> = line.split(' ').collect();
-    match tokens[0] {
-        "addx" => Add(tokens[1].parse().expect("Error parsing token into i32")),
-        "noop" => Noop,
-        _ => panic!("Unknown instruction in input file"),
+mod asm;
+mod instructions;
+
+use instructions::{parse_line, Flow, Instruction, Registers, X};
+
+/// every instruction in `fname`, blank lines are skipped. Exits with the
+/// line number on anything that doesn't parse.
+fn read_program(fname: &str) -> Vec<Instruction> {
+    let file = File::open(fname).unwrap_or_else(|err| {
+        eprintln!("{}: {}", fname, err);
+        process::exit(1);
+    });
+
+    let mut program = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.expect("error reading input file");
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(&line) {
+            Ok(instruction) => program.push(instruction),
+            Err(err) => {
+                eprintln!("{}:{}: {}", fname, i + 1, err);
+                process::exit(1);
+            }
+        }
     }
+    program
 }
 
-fn is_interesting(cycle: i32) -> bool {
-    cycle % 40 == 20
+/// what X did over one clock cycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tick {
+    /// counting from 1
+    cycle: i32,
+    /// the instruction that was running
+    pc: usize,
+    /// X while the cycle is going on, what the puzzle asks about
+    during: i32,
+    /// X once the cycle is over, after any instruction that finished
+    after: i32,
 }
 
-///@return Option<(i32, i32)> a tuple (cycle, reg) which is the "interesting"
-/// cycle we run into during execution of the instruction, and their register value
-/// None if we didn't run into an "interesting" cycle.
-fn execute_instruction(cycle: &mut i32, reg: &mut i32, command: Instruction) -> Option<(i32, i32)> {
-    let mut interesting = None;
-    match command {
-        Add(x) => {
-            if is_interesting(*cycle + 1) {
-                interesting = Some((*cycle + 1, *reg));
-            }
-            *reg += x;
-            *cycle += 2;
+/// Runs a program one clock cycle at a time, as an iterator of `Tick`s.
+/// It ends when the program runs off either end, jumps included, which
+/// a program that loops may never do.
+struct Cpu {
+    program: Vec<Instruction>,
+    /// the instruction being executed
+    pc: usize,
+    /// cycles spent on it so far
+    busy: u32,
+    registers: Registers,
+    cycle: i32,
+}
+
+impl Cpu {
+    /// X starts at 1, every other register at 0
+    fn new(program: Vec<Instruction>) -> Cpu {
+        let mut registers = Registers::default();
+        registers[X] = 1;
+        Cpu {
+            program,
+            pc: 0,
+            busy: 0,
+            registers,
+            cycle: 0,
         }
-        Noop => *cycle += 1,
-    }
-    if is_interesting(*cycle) {
-        interesting = Some((*cycle, *reg));
     }
-    interesting
 }
 
-fn solve1(fname: &str) -> i32 {
-    let file = File::open(fname).unwrap();
-    let lines = BufReader::new(file).lines();
-    let mut cycle = 1;
-    let mut reg = 1;
-    let mut sum = 0;
+impl Iterator for Cpu {
+    type Item = Tick;
 
-    for line in lines {
-        let line = line.expect("error reading input file");
-        let command = parse_line(&line);
+    fn next(&mut self) -> Option<Tick> {
+        let instruction = *self.program.get(self.pc)?;
+        self.cycle += 1;
+        let pc = self.pc;
+        let during = self.registers[X];
 
-        if let Some(x) = execute_instruction(&mut cycle, &mut reg, command) {
-            sum += x.0 * x.1;
-        };
+        self.busy += 1;
+        if self.busy == instruction.cycles() {
+            self.busy = 0;
+            self.pc = match instruction.execute(&mut self.registers) {
+                Flow::Next => self.pc + 1,
+                // off the start is as good as off the end
+                Flow::Jump(offset) => self
+                    .pc
+                    .checked_add_signed(offset as isize)
+                    .unwrap_or(usize::MAX),
+            };
+        }
+
+        Some(Tick {
+            cycle: self.cycle,
+            pc,
+            during,
+            after: self.registers[X],
+        })
     }
+}
+
+fn is_interesting(cycle: i32) -> bool {
+    cycle % 40 == 20
+}
+
+/// the puzzle's sum, which only looks as far as cycle 220
+fn signal_strength(program: Vec<Instruction>) -> i32 {
+    Cpu::new(program)
+        .take(220)
+        .filter(|tick| is_interesting(tick.cycle))
+        .map(|tick| tick.cycle * tick.during)
+        .sum()
+}
 
-    sum
+fn solve1(fname: &str) -> i32 {
+    signal_strength(read_program(fname))
 }
 
 const CRT_COLUMNS: usize = 40;
 const CRT_ROWS: usize = 6;
-fn solve2(fname: &str) -> String {
-    let file = File::open(fname).unwrap();
-    let mut lines = BufReader::new(file).lines();
-    let mut reg = 1;
-    let mut pending_add: Option<i32> = None;
-    let mut crt = vec![b' '; CRT_COLUMNS * CRT_ROWS];
 
-    for cycle in 1..CRT_ROWS * CRT_COLUMNS + 1 {
-        if let Some(x) = pending_add.take() {
-            reg += x;
-        } else {
-            let line = lines
-                .next()
-                .expect("no more input in file")
-                .expect("error reading input file");
-
-            match parse_line(&line) {
-                Noop => (),
-                Add(x) => pending_add = Some(x),
-            }
-        }
+/// Draws a pixel on every cycle, lit when the three pixel wide sprite
+/// centred on X covers it. The screen stays dark after the program ends.
+fn draw(program: Vec<Instruction>) -> String {
+    let mut crt = vec![b' '; CRT_COLUMNS * CRT_ROWS];
 
-        if ((cycle % CRT_COLUMNS) as i32).abs_diff(reg) <= 1 {
-            // draw
-            crt[cycle] = b'#';
+    for tick in Cpu::new(program).take(crt.len()) {
+        let pixel = tick.cycle as usize - 1;
+        if ((pixel % CRT_COLUMNS) as i32).abs_diff(tick.during) <= 1 {
+            crt[pixel] = b'#';
         }
     }
 
@@ -100,20 +147,151 @@ fn solve2(fname: &str) -> String {
     lines.join("\n")
 }
 
+fn solve2(fname: &str) -> String {
+    draw(read_program(fname))
+}
+
 fn main() {
-    let x = solve1("input.txt");
-    println!("{}", x);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        ["assemble", "--text", text] => match asm::text_bitmap(text) {
+            Ok(bitmap) => assemble(&bitmap),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        },
+        ["assemble", fname] => {
+            let picture = fs::read_to_string(fname).unwrap_or_else(|err| {
+                eprintln!("{}: {}", fname, err);
+                process::exit(1);
+            });
+            match asm::parse_bitmap(&picture) {
+                Ok(bitmap) => assemble(&bitmap),
+                Err(err) => {
+                    eprintln!("{}: {}", fname, err);
+                    process::exit(1);
+                }
+            }
+        }
+        ["list", fname] => print!("{}", asm::listing(&read_program(fname), 240)),
+        ["list", fname, cycles] => {
+            let cycles = cycles.parse().unwrap_or_else(|_| {
+                eprintln!("{:?} should be a number of cycles", cycles);
+                process::exit(1);
+            });
+            print!("{}", asm::listing(&read_program(fname), cycles))
+        }
+        [] | [_] => {
+            let fname = args.first().copied().unwrap_or("input.txt");
+            let x = solve1(fname);
+            println!("{}", x);
+
+            let y = solve2(fname);
+            println!("{}", y);
+        }
+        _ => {
+            eprintln!("usage: day10 [input file]");
+            eprintln!("       day10 assemble <picture file> | --text <letters>");
+            eprintln!("       day10 list <program file> [cycles]");
+            process::exit(1);
+        }
+    }
+}
 
-    let y = solve2("input.txt");
-    println!("{}", y);
+/// prints a program that draws `bitmap`, one instruction per line
+fn assemble(bitmap: &asm::Bitmap) {
+    match asm::assemble(bitmap) {
+        Ok(program) => {
+            for instruction in program {
+                println!("{}", instruction);
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::solve1;
+    use crate::{parse_line, solve1, solve2, Cpu, Instruction};
+
+    fn program(lines: &[&str]) -> Vec<Instruction> {
+        lines.iter().map(|line| parse_line(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_trace() {
+        let ticks: Vec<_> = Cpu::new(program(&["noop", "addx 3", "addx -5"]))
+            .map(|tick| (tick.cycle, tick.during, tick.after))
+            .collect();
+        assert_eq!(
+            ticks,
+            [(1, 1, 1), (2, 1, 1), (3, 1, 4), (4, 4, 4), (5, 4, -1)]
+        );
+        assert!(Cpu::new(vec![]).next().is_none());
+    }
+
+    #[test]
+    fn test_loop() {
+        // doubles x once for every count of y
+        let cpu = Cpu::new(program(&[
+            "addy 3",
+            "addx 2",
+            "mulx 2",
+            "addy -1",
+            "jnz y -2",
+            "jz y +2",
+            "addx 1000",
+            "noop",
+        ]));
+        let ticks: Vec<_> = cpu.collect();
+        let last = ticks.last().unwrap();
+
+        assert_eq!(last.after, 24);
+        assert_eq!(last.cycle, 2 + 2 + 3 * (3 + 2 + 1) + 1 + 1);
+        // x is 12 while the last mulx runs
+        assert_eq!(ticks[17].during, 12);
+    }
+
+    #[test]
+    fn test_jumps_out() {
+        assert_eq!(Cpu::new(program(&["jmp 5", "noop"])).count(), 1);
+        assert_eq!(Cpu::new(program(&["noop", "jmp -2", "noop"])).count(), 2);
+        // loops forever
+        assert_eq!(Cpu::new(program(&["jmp 0"])).take(100).count(), 100);
+        assert_eq!(
+            Cpu::new(program(&["jgz x 0"]))
+                .take(100)
+                .last()
+                .unwrap()
+                .cycle,
+            100
+        );
+    }
 
     #[test]
     fn test_solve1() {
         assert_eq!(solve1("test_input.txt"), 13140);
     }
+
+    #[test]
+    fn test_solve2() {
+        let expected = [
+            "##..##..##..##..##..##..##..##..##..##..",
+            "###...###...###...###...###...###...###.",
+            "####....####....####....####....####....",
+            "#####.....#####.....#####.....#####.....",
+            "######......######......######......####",
+            "#######.......#######.......#######.....",
+        ];
+        assert_eq!(
+            solve2("test_input.txt"),
+            expected.join("\n").replace('.', " ")
+        );
+    }
 }
@@ -0,0 +1,197 @@
+//! Comparing two captures of the same filesystem.
+
+use crate::{query, Directory, FileSize, Node};
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Resized,
+}
+
+/// One difference between two trees. A directory is `Resized` whenever
+/// anything below it changed size, so growth shows up on every ancestor.
+/// Added or removed directories are reported once, not file by file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    pub path: String,
+    pub is_dir: bool,
+    pub kind: ChangeKind,
+    /// 0 for added nodes
+    pub old_size: usize,
+    /// 0 for removed nodes
+    pub new_size: usize,
+}
+
+impl Change {
+    pub fn delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.is_dir { "dir" } else { "file" };
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {} ({}, size={})", self.path, kind, self.new_size),
+            ChangeKind::Removed => write!(f, "- {} ({}, size={})", self.path, kind, self.old_size),
+            ChangeKind::Resized => write!(
+                f,
+                "~ {} ({}, {} -> {}, {:+})",
+                self.path,
+                kind,
+                self.old_size,
+                self.new_size,
+                self.delta()
+            ),
+        }
+    }
+}
+
+/// every difference between `old` and `new`, parents before their children
+pub fn diff(old: &Rc<RefCell<Directory>>, new: &Rc<RefCell<Directory>>) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_dirs(&mut changes, old, new, &query::path_of(new));
+    changes
+}
+
+fn diff_dirs(
+    changes: &mut Vec<Change>,
+    old: &Rc<RefCell<Directory>>,
+    new: &Rc<RefCell<Directory>>,
+    path: &str,
+) {
+    let (old_size, new_size) = (old.borrow().calculate_size(), new.borrow().calculate_size());
+    if old_size != new_size {
+        changes.push(Change {
+            path: path.to_string(),
+            is_dir: true,
+            kind: ChangeKind::Resized,
+            old_size,
+            new_size,
+        });
+    }
+
+    // whatever was there before in its old order, then whatever is new
+    let mut names: Vec<String> = old.borrow().children.iter().map(Node::name).collect();
+    for node in new.borrow().children.iter() {
+        let name = node.name();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    for name in names {
+        let child_path = query::join(path, &name);
+        let old_child = old.borrow().get_node(&name).cloned();
+        let new_child = new.borrow().get_node(&name).cloned();
+
+        match (old_child, new_child) {
+            (Some(Node::Dir(old)), Some(Node::Dir(new))) => {
+                diff_dirs(changes, &old, &new, &child_path)
+            }
+            (Some(Node::Reg(old)), Some(Node::Reg(new))) => {
+                if old.size != new.size {
+                    changes.push(Change {
+                        path: child_path,
+                        is_dir: false,
+                        kind: ChangeKind::Resized,
+                        old_size: old.size,
+                        new_size: new.size,
+                    });
+                }
+            }
+            (old, new) => {
+                // also covers a file turning into a directory or back
+                if let Some(old) = old {
+                    changes.push(Change {
+                        path: child_path.clone(),
+                        is_dir: matches!(old, Node::Dir(_)),
+                        kind: ChangeKind::Removed,
+                        old_size: old.calc_size(),
+                        new_size: 0,
+                    });
+                }
+                if let Some(new) = new {
+                    changes.push(Change {
+                        path: child_path,
+                        is_dir: matches!(new, Node::Dir(_)),
+                        kind: ChangeKind::Added,
+                        old_size: 0,
+                        new_size: new.calc_size(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff;
+    use crate::parsing;
+
+    #[test]
+    fn test_diff_identical() {
+        let old = parsing::parse_input("test_input.txt");
+        let new = parsing::parse_input("test_input.txt");
+
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = parsing::parse_input("test_input.txt");
+        let new = parsing::parse_str(
+            "\
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+100 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+600 i
+dir x
+$ cd x
+$ ls
+50 y
+$ cd ..
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+",
+        );
+
+        let changes: Vec<String> = diff(&old, &new)
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+
+        assert_eq!(
+            changes,
+            [
+                "~ / (dir, 48381165 -> 32662879, -15718286)",
+                "~ /a (dir, 94853 -> 94919, +66)",
+                "~ /a/e (dir, 584 -> 650, +66)",
+                "~ /a/e/i (file, 584 -> 600, +16)",
+                "+ /a/e/x (dir, size=50)",
+                "~ /c.dat (file, 8504156 -> 100, -8504056)",
+                "~ /d (dir, 24933642 -> 17719346, -7214296)",
+                "- /d/k (file, size=7214296)",
+            ]
+        );
+    }
+}
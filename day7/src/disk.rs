@@ -0,0 +1,185 @@
+//! Going between a `Directory` tree and a real directory on disk.
+//!
+//! Files are created sparse with `File::set_len`, so materializing a tree
+//! that claims to hold gigabytes doesn't actually use that much space.
+
+use crate::{Directory, Node};
+use std::{
+    cell::RefCell,
+    env,
+    fmt::Write,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// creates everything inside `dir` under `target`, which must already exist
+pub fn materialize(dir: &Rc<RefCell<Directory>>, target: &Path) -> io::Result<()> {
+    for child in dir.borrow().children.iter() {
+        let path = target.join(checked_name(&child.name())?);
+        match child {
+            Node::Reg(file) => File::create(&path)?.set_len(file.size as u64)?,
+            Node::Dir(child) => {
+                fs::create_dir(&path)?;
+                materialize(child, &path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// materializes `dir` into a fresh directory under the system's temp
+/// directory and returns its path. Removing it is up to the caller.
+pub fn materialize_temp(dir: &Rc<RefCell<Directory>>) -> io::Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    let target = env::temp_dir().join(format!(
+        "day7-{}-{}-{}",
+        process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    fs::create_dir(&target)?;
+    if let Err(err) = materialize(dir, &target) {
+        let _ = fs::remove_dir_all(&target);
+        return Err(err);
+    }
+    Ok(target)
+}
+
+/// names from a transcript end up as path components, so they can't be
+/// allowed to point anywhere else
+fn checked_name(name: &str) -> io::Result<&str> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} can't be used as a file name", name),
+        ));
+    }
+    Ok(name)
+}
+
+/// walks a real directory and writes the `$ cd`/`$ ls` session that would
+/// have explored it, starting with `$ cd /`. Entries are listed by name,
+/// symlinks and other special files are left out.
+pub fn transcript_from_disk(path: &Path) -> io::Result<String> {
+    let mut out = String::from("$ cd /\n");
+    write_transcript(&mut out, path)?;
+    Ok(out)
+}
+
+fn write_transcript(out: &mut String, path: &Path) -> io::Result<()> {
+    let mut entries = vec![];
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not valid UTF-8", name),
+            )
+        })?;
+        if name.contains('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} can't be written to a transcript", name),
+            ));
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            entries.push((name, None));
+        } else if file_type.is_file() {
+            entries.push((name, Some(entry.metadata()?.len())));
+        }
+    }
+    entries.sort();
+
+    out.push_str("$ ls\n");
+    for (name, size) in entries.iter() {
+        match size {
+            Some(size) => writeln!(out, "{} {}", size, name).unwrap(),
+            None => writeln!(out, "dir {}", name).unwrap(),
+        }
+    }
+
+    for (name, _) in entries.iter().filter(|(_, size)| size.is_none()) {
+        writeln!(out, "$ cd {}", name).unwrap();
+        write_transcript(out, &path.join(name))?;
+        out.push_str("$ cd ..\n");
+    }
+
+    Ok(())
+}
+
+/// apparent size of the regular files under `path`. That's what
+/// `du -sb` reports, minus what the directories themselves take up.
+pub fn disk_usage(path: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total += disk_usage(&entry.path())?;
+        } else if file_type.is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disk_usage, materialize_temp, transcript_from_disk};
+    use crate::{parsing, query, FileSize, Node};
+    use std::fs;
+
+    #[test]
+    fn test_materialize() {
+        let root = parsing::parse_input("test_input.txt");
+        let target = materialize_temp(&root).unwrap();
+
+        assert_eq!(fs::metadata(target.join("a/e/i")).unwrap().len(), 584);
+        assert!(target.join("d").is_dir());
+        assert_eq!(
+            disk_usage(&target).unwrap() as usize,
+            root.borrow().calculate_size()
+        );
+        assert_eq!(disk_usage(&target.join("a")).unwrap(), 94853);
+
+        fs::remove_dir_all(target).unwrap();
+    }
+
+    #[test]
+    fn test_materialize_rejects_bad_names() {
+        let root = parsing::parse_str("$ cd /\n$ ls\n1 ..\n");
+        assert!(materialize_temp(&root).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sorted_sizes = |transcript: &str| {
+            let root = parsing::parse_str(transcript);
+            let mut sizes: Vec<(String, usize, bool)> = query::walk(&root)
+                .map(|(path, node)| (path, node.calc_size(), matches!(node, Node::Dir(_))))
+                .collect();
+            sizes.sort();
+            sizes
+        };
+
+        let original = fs::read_to_string("test_input.txt").unwrap();
+        let target = materialize_temp(&parsing::parse_str(&original)).unwrap();
+        let transcript = transcript_from_disk(&target).unwrap();
+        fs::remove_dir_all(target).unwrap();
+
+        assert!(transcript.starts_with("$ cd /\n$ ls\ndir a\n14848514 b.txt\n"));
+        assert_eq!(sorted_sizes(&transcript), sorted_sizes(&original));
+    }
+}
@@ -1,8 +1,23 @@
 use std::{
     cell::RefCell,
+    env,
     fmt::Display,
+    fs,
+    io::{self, IsTerminal},
+    path::Path,
     rc::{Rc, Weak},
 };
+
+mod diff;
+mod disk;
+mod planner;
+mod query;
+mod render;
+mod shell;
+mod transcript;
+
+use planner::Planner;
+
 const FS_MAX: usize = 70000000;
 const SPACE_NEEDED: usize = 30000000;
 
@@ -10,17 +25,19 @@ const SPACE_NEEDED: usize = 30000000;
 pub struct Directory {
     name: String,
     parent: Option<Weak<RefCell<Directory>>>,
-    children: Vec<Box<Node>>,
+    children: Vec<Node>,
 }
 
 #[allow(dead_code)]
+#[derive(Clone)]
 pub struct RegFile {
     name: String,
     parent: Weak<RefCell<Directory>>,
     size: usize,
 }
 
-enum Node {
+#[derive(Clone)]
+pub enum Node {
     Reg(RegFile),
     Dir(Rc<RefCell<Directory>>),
 }
@@ -36,6 +53,13 @@ impl Node {
             Self::Dir(dir) => dir.as_ref().borrow().calculate_size(),
         }
     }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Reg(file) => file.name.clone(),
+            Self::Dir(dir) => dir.borrow().name.clone(),
+        }
+    }
 }
 
 impl FileSize for RegFile {
@@ -48,21 +72,31 @@ impl FileSize for Directory {
     fn calculate_size(&self) -> usize {
         self.children
             .iter()
-            .fold(0, |acc_size, node| acc_size + node.as_ref().calc_size())
+            .fold(0, |acc_size, node| acc_size + node.calc_size())
     }
 }
 
 impl Directory {
-    fn add_child(&mut self, node: Box<Node>) {
+    fn add_child(&mut self, node: Node) {
         self.children.push(node);
     }
 
-    fn get_child(&self, name: &str) -> Option<&Box<Node>> {
-        self.children.iter().find(|&node| match node.as_ref() {
+    fn get_child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|&node| match node {
             Node::Dir(dir) => dir.as_ref().borrow().name == name,
             Node::Reg(_) => false,
         })
     }
+
+    /// like `get_child`, but also matches regular files
+    fn get_node(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|&node| node.name() == name)
+    }
+
+    fn remove_child(&mut self, name: &str) -> Option<Node> {
+        let pos = self.children.iter().position(|node| node.name() == name)?;
+        Some(self.children.remove(pos))
+    }
 }
 
 impl Display for Directory {
@@ -72,7 +106,7 @@ impl Display for Directory {
 }
 
 pub mod parsing {
-    use crate::{Directory, Node, RegFile};
+    use crate::{query, Directory, Node, RegFile};
     use std::{
         cell::RefCell,
         fs::File,
@@ -82,8 +116,18 @@ pub mod parsing {
 
     pub fn parse_input(file_name: &str) -> Rc<RefCell<Directory>> {
         let file = File::open(file_name).unwrap();
-        let mut lines = io::BufReader::new(file).lines();
+        let lines = io::BufReader::new(file).lines().map_while(Result::ok);
 
+        parse_lines(lines)
+    }
+
+    /// same as `parse_input`, for a transcript that's already in memory
+    #[cfg(test)]
+    pub fn parse_str(transcript: &str) -> Rc<RefCell<Directory>> {
+        parse_lines(transcript.lines().map(String::from))
+    }
+
+    fn parse_lines(mut lines: impl Iterator<Item = String>) -> Rc<RefCell<Directory>> {
         let root = Rc::new(RefCell::new(Directory {
             name: "/".to_string(),
             parent: None,
@@ -93,8 +137,9 @@ pub mod parsing {
 
         lines.next();
 
-        while let Some(Ok(line)) = lines.next() {
-            let tokens: Vec<&str> = line.split(' ').collect();
+        for line in lines {
+            // names may contain spaces, so don't split them up
+            let tokens: Vec<&str> = line.splitn(3, ' ').collect();
             if tokens[0] == "$" {
                 if tokens[1] == "cd" {
                     pwd = change_dir(pwd.clone(), tokens[2]);
@@ -109,7 +154,11 @@ pub mod parsing {
         root
     }
 
-    fn change_dir<'a>(pwd: Rc<RefCell<Directory>>, new_dir: &str) -> Rc<RefCell<Directory>> {
+    pub fn change_dir(pwd: Rc<RefCell<Directory>>, new_dir: &str) -> Rc<RefCell<Directory>> {
+        if new_dir == "/" {
+            return query::root_of(&pwd);
+        }
+
         if new_dir == ".." {
             return match &pwd.as_ref().borrow().parent {
                 Some(parent) => parent.upgrade().expect("should be available").clone(),
@@ -119,44 +168,44 @@ pub mod parsing {
 
         let pnode = pwd.as_ref().borrow();
 
-        if let Node::Dir(pdir) = &*pnode.get_child(new_dir).unwrap().as_ref() {
-            Rc::clone(&pdir)
+        if let Node::Dir(pdir) = pnode.get_child(new_dir).unwrap() {
+            Rc::clone(pdir)
         } else {
             panic!("")
         }
     }
 
-    fn record_dir(pwd: Rc<RefCell<Directory>>, line: &str) {
-        let tokens: Vec<&str> = line.split(' ').collect();
+    pub fn record_dir(pwd: Rc<RefCell<Directory>>, line: &str) {
+        let tokens: Vec<&str> = line.splitn(2, ' ').collect();
         let node = match tokens[0] {
-            "dir" => Box::new(Node::Dir(Rc::new(RefCell::new(Directory {
+            "dir" => Node::Dir(Rc::new(RefCell::new(Directory {
                 name: String::from(tokens[1]),
                 parent: Some(Rc::downgrade(&pwd)),
                 children: Vec::new(),
-            })))),
-            sz => Box::new(Node::Reg(RegFile {
+            }))),
+            sz => Node::Reg(RegFile {
                 name: String::from(tokens[1]),
                 parent: Rc::downgrade(&pwd),
                 size: sz
                     .parse()
-                    .expect(&format!("{} could not be parsed into usize", sz)),
-            })),
+                    .unwrap_or_else(|_| panic!("{} could not be parsed into usize", sz)),
+            }),
         };
 
         pwd.borrow_mut().add_child(node);
     }
 }
 
-fn find_smallish_dirs<'a>(root: Rc<RefCell<Directory>>) -> Vec<Rc<RefCell<Directory>>> {
+fn find_smallish_dirs(root: Rc<RefCell<Directory>>) -> Vec<Rc<RefCell<Directory>>> {
     let mut ans = vec![];
     let r = root.borrow();
-    let dirs = r.children.iter().filter(|&node| match **node {
-        Node::Dir(_) => true,
-        _ => false,
-    });
+    let dirs = r
+        .children
+        .iter()
+        .filter(|&node| matches!(node, Node::Dir(_)));
 
     for dir in dirs {
-        if let Node::Dir(dir) = &**dir {
+        if let Node::Dir(dir) = dir {
             if dir.borrow().calculate_size() <= 100000 {
                 ans.push(dir.clone());
             }
@@ -167,42 +216,6 @@ fn find_smallish_dirs<'a>(root: Rc<RefCell<Directory>>) -> Vec<Rc<RefCell<Direct
     ans
 }
 
-fn find_smallest_big_enough(root: Rc<RefCell<Directory>>) -> usize {
-    let occupied = root.borrow().calculate_size();
-    let free_space = FS_MAX - occupied;
-    let space_needed = SPACE_NEEDED - free_space;
-
-    let r = root.borrow();
-
-    let extract_dirs = |node: &Box<Node>| match &**node {
-        Node::Dir(dir) => Some(dir.clone()),
-        _ => None,
-    };
-
-    let mut queue: Vec<Rc<RefCell<Directory>>> =
-        r.children.iter().filter_map(extract_dirs).collect();
-
-    let mut min_size = FS_MAX;
-
-    while queue.len() > 0 {
-        let dir = queue.pop().unwrap();
-
-        let dir_size = dir.borrow().calculate_size();
-        if dir_size < space_needed {
-            // skip children, they're all too small
-            continue;
-        }
-
-        queue.extend(dir.borrow().children.iter().filter_map(extract_dirs));
-
-        if dir_size < min_size {
-            min_size = dir_size;
-        }
-    }
-
-    min_size
-}
-
 fn solve1(file_name: &str) -> usize {
     let root = parsing::parse_input(file_name);
     let small_dirs = find_smallish_dirs(root);
@@ -214,22 +227,199 @@ fn solve1(file_name: &str) -> usize {
 
 fn solve2(file_name: &str) -> usize {
     let root = parsing::parse_input(file_name);
-    find_smallest_big_enough(root)
+    Planner::new(FS_MAX, SPACE_NEEDED)
+        .smallest_single(&root)
+        .expect("some directory should be big enough")
+        .freed
 }
 
 fn main() {
-    let ans = solve1("input.txt");
-    println!("{}", ans);
-    let ans = solve2("input.txt");
-    println!("{}", ans);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        [] => {
+            let ans = solve1("input.txt");
+            println!("{}", ans);
+            let ans = solve2("input.txt");
+            println!("{}", ans);
+        }
+        ["size", file_name, path] => {
+            let root = parsing::parse_input(file_name);
+            match query::size_of(&root, path) {
+                Some(size) => println!("{}", size),
+                None => eprintln!("{}: no such file or directory", path),
+            }
+        }
+        ["find", file_name, pattern] | ["find", file_name, pattern, _] => {
+            let min_size: usize = match args.get(3) {
+                Some(sz) => sz.parse().expect("min size should be a number"),
+                None => 0,
+            };
+            let root = parsing::parse_input(file_name);
+            for (path, node) in query::find(&root, pattern) {
+                let size = node.calc_size();
+                if size >= min_size {
+                    println!("{} {}", size, path);
+                }
+            }
+        }
+        ["tree", file_name] => {
+            let root = parsing::parse_input(file_name);
+            print!("{}", render::tree(&root));
+        }
+        ["du", file_name, ref opts @ ..] => {
+            let human_readable = opts.contains(&"-h");
+            let max_depth = opts
+                .iter()
+                .find(|&&opt| opt != "-h")
+                .map(|depth| depth.parse().expect("depth should be a number"));
+            let root = parsing::parse_input(file_name);
+            print!(
+                "{}",
+                render::format_du(&render::du(&root, max_depth), human_readable)
+            );
+        }
+        ["json", file_name] => {
+            let root = parsing::parse_input(file_name);
+            println!("{}", render::json(&root));
+        }
+        ["plan", file_name, capacity, required] | ["plan", file_name, capacity, required, _] => {
+            let capacity = capacity.parse().expect("capacity should be a number");
+            let required = required.parse().expect("required space should be a number");
+            let planner = Planner::new(capacity, required);
+            let root = parsing::parse_input(file_name);
+
+            let plan = if args.get(4) == Some(&"--single") {
+                planner.smallest_single(&root)
+            } else {
+                planner.optimal(&root)
+            };
+            match plan {
+                Some(plan) => {
+                    println!("{}", plan.freed);
+                    for dir in plan.dirs {
+                        println!("{}", dir);
+                    }
+                }
+                None => eprintln!("deleting directories can't free enough space"),
+            }
+        }
+        ["materialize", file_name] => {
+            let root = parsing::parse_input(file_name);
+            let target = disk::materialize_temp(&root).expect("failed to materialize tree");
+            println!("{}", target.display());
+        }
+        ["materialize", file_name, target] => {
+            let root = parsing::parse_input(file_name);
+            fs::create_dir_all(target).expect("failed to create target directory");
+            disk::materialize(&root, Path::new(target)).expect("failed to materialize tree");
+        }
+        ["transcript", dir] => {
+            let transcript =
+                disk::transcript_from_disk(Path::new(dir)).expect("failed to read directory");
+            print!("{}", transcript);
+        }
+        ["usage", dir] => {
+            let size = disk::disk_usage(Path::new(dir)).expect("failed to read directory");
+            println!("{}", size);
+        }
+        ["gen", seed, ref opts @ ..] => {
+            let mut config = transcript::GenConfig {
+                seed: seed.parse().expect("seed should be a number"),
+                ..Default::default()
+            };
+            if let Some(depth) = opts.first() {
+                config.depth = depth.parse().expect("depth should be a number");
+            }
+            if let Some(fan_out) = opts.get(1) {
+                config.fan_out = fan_out.parse().expect("fan out should be a number");
+            }
+            if let [kind, min, max] = opts[2.min(opts.len())..] {
+                let min = min.parse().expect("min size should be a number");
+                let max = max.parse().expect("max size should be a number");
+                config.sizes =
+                    transcript::SizeDistribution::new(kind, min, max).unwrap_or_else(|err| {
+                        eprintln!("{}", err);
+                        eprintln!(
+                            "usage: day7 gen <seed> [depth] [fan out] [uniform|log <min> <max>]"
+                        );
+                        std::process::exit(1);
+                    });
+            } else if opts.len() > 2 {
+                eprintln!("usage: day7 gen <seed> [depth] [fan out] [uniform|log <min> <max>]");
+                std::process::exit(1);
+            }
+
+            let generated = transcript::generate(&config);
+            print!("{}", generated.transcript);
+            eprintln!("{}", generated.small_dirs_total);
+            match generated.smallest_big_enough {
+                Some(size) => eprintln!("{}", size),
+                None => eprintln!("no directory is big enough"),
+            }
+        }
+        ["check", file_name] => {
+            let transcript = fs::read_to_string(file_name).expect("failed to read transcript");
+            let issues = transcript::check(&transcript);
+            for issue in issues.iter() {
+                println!("{}", issue);
+            }
+            if !issues.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        ["shell", file_name] | ["shell", file_name, _, _] => {
+            let planner = match args[2..] {
+                [capacity, required] => Planner::new(
+                    capacity.parse().expect("capacity should be a number"),
+                    required.parse().expect("required space should be a number"),
+                ),
+                _ => Planner::new(FS_MAX, SPACE_NEEDED),
+            };
+            let mut shell = shell::Shell::new(parsing::parse_input(file_name), planner);
+            shell.prompt = io::stdin().is_terminal();
+            shell
+                .run(io::stdin().lock(), io::stdout())
+                .expect("failed to run shell");
+        }
+        ["diff", old, new] => {
+            let old = parsing::parse_input(old);
+            let new = parsing::parse_input(new);
+            for change in diff::diff(&old, &new) {
+                println!("{}", change);
+            }
+        }
+        _ => {
+            eprintln!("usage: day7");
+            eprintln!("       day7 size <transcript> <path>");
+            eprintln!("       day7 find <transcript> <pattern> [min size]");
+            eprintln!("       day7 tree <transcript>");
+            eprintln!("       day7 du <transcript> [-h] [max depth]");
+            eprintln!("       day7 json <transcript>");
+            eprintln!("       day7 plan <transcript> <capacity> <required> [--single]");
+            eprintln!("       day7 materialize <transcript> [target]");
+            eprintln!("       day7 transcript <dir>");
+            eprintln!("       day7 usage <dir>");
+            eprintln!("       day7 gen <seed> [depth] [fan out] [uniform|log <min> <max>]");
+            eprintln!("       day7 check <transcript>");
+            eprintln!("       day7 shell <transcript> [capacity required]");
+            eprintln!("       day7 diff <old transcript> <new transcript>");
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::solve1;
+    use crate::{solve1, solve2};
 
     #[test]
     fn test_parse_input() {
         assert_eq!(solve1("test_input.txt"), 95437);
     }
+
+    #[test]
+    fn test_solve2() {
+        assert_eq!(solve2("test_input.txt"), 24933642);
+    }
 }
@@ -0,0 +1,383 @@
+//! Deciding which directories to delete to get enough free space.
+
+use crate::{query, Directory, FileSize, Node};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// a set of directories to delete and how much space that frees
+#[derive(Debug, PartialEq, Eq)]
+pub struct Plan {
+    pub dirs: Vec<String>,
+    pub freed: usize,
+}
+
+pub struct Planner {
+    capacity: usize,
+    required: usize,
+}
+
+impl Planner {
+    /// `capacity` is the total size of the disk, `required` is how much of it
+    /// must be free in the end
+    pub fn new(capacity: usize, required: usize) -> Planner {
+        Planner { capacity, required }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// how much has to be deleted, 0 if there's already enough free space
+    pub fn space_to_free(&self, root: &Rc<RefCell<Directory>>) -> usize {
+        let occupied = root.borrow().calculate_size();
+        let free_space = self.capacity.saturating_sub(occupied);
+        self.required.saturating_sub(free_space)
+    }
+
+    /// the smallest single directory that frees enough space. `None` if
+    /// no directory is big enough, the root is never considered.
+    pub fn smallest_single(&self, root: &Rc<RefCell<Directory>>) -> Option<Plan> {
+        let space_needed = self.space_to_free(root);
+        if space_needed == 0 {
+            return Some(Plan {
+                dirs: vec![],
+                freed: 0,
+            });
+        }
+
+        let mut queue = subdirs(root, &query::path_of(root));
+        let mut best: Option<Plan> = None;
+
+        while let Some((path, dir)) = queue.pop() {
+            let dir_size = dir.borrow().calculate_size();
+            if dir_size < space_needed {
+                // skip children, they're all too small
+                continue;
+            }
+
+            queue.extend(subdirs(&dir, &path));
+
+            let better = match &best {
+                Some(best) => dir_size < best.freed,
+                None => true,
+            };
+            if better {
+                best = Some(Plan {
+                    dirs: vec![path],
+                    freed: dir_size,
+                });
+            }
+        }
+
+        best
+    }
+
+    /// the set of directories whose combined size is the smallest that
+    /// still frees enough space. A directory and one of its descendants are
+    /// never picked together, so nothing is counted twice. `None` if even
+    /// deleting everything below the root isn't enough.
+    ///
+    /// This is a subset sum over the tree: it takes time proportional to
+    /// the number of directories times the amount that has to be freed.
+    pub fn optimal(&self, root: &Rc<RefCell<Directory>>) -> Option<Plan> {
+        let space_needed = self.space_to_free(root);
+        if space_needed == 0 {
+            return Some(Plan {
+                dirs: vec![],
+                freed: 0,
+            });
+        }
+
+        let mut dirs = vec![];
+        let mut first = vec![];
+        for (path, dir) in subdirs(root, &query::path_of(root)) {
+            post_order(dir, path, &mut dirs, &mut first);
+        }
+        let sizes: Vec<usize> = dirs
+            .iter()
+            .map(|(_, dir)| dir.borrow().calculate_size())
+            .collect();
+
+        let search = SubsetSum {
+            sizes: &sizes,
+            first: &first,
+            space_needed,
+        };
+
+        let (freed, mut last, mut sum) = search.run(dirs.len(), None).best?;
+        let mut picked = vec![last];
+        while sum != 0 {
+            // `sum` was reachable without touching `last`'s subtree, find
+            // the directory that made it reachable
+            last = search
+                .run(first[last], Some(sum))
+                .reached_by
+                .expect("sum should be reachable");
+            picked.push(last);
+            sum -= sizes[last];
+        }
+
+        let mut paths: Vec<String> = picked.into_iter().map(|i| dirs[i].0.clone()).collect();
+        paths.sort();
+
+        Some(Plan { dirs: paths, freed })
+    }
+}
+
+/// Subset sum over directories in post-order, where everything inside a
+/// directory comes right before it and `first[i]` is where the subtree of
+/// directory `i` starts.
+///
+/// The set of reachable sums only grows, so the sums that don't use
+/// anything inside directory `i` are exactly the ones reachable before
+/// `first[i]`. Those are the only ones `i` can be added to.
+struct SubsetSum<'a> {
+    sizes: &'a [usize],
+    first: &'a [usize],
+    space_needed: usize,
+}
+
+struct Outcome {
+    /// (total, last directory, sum before the last directory), the smallest
+    /// total that frees enough space
+    best: Option<(usize, usize, usize)>,
+    /// the directory after which the watched sum became reachable
+    reached_by: Option<usize>,
+}
+
+impl SubsetSum<'_> {
+    /// runs the first `until` directories, stopping early once `watch` is
+    /// reachable or an exact fit is found
+    fn run(&self, until: usize, watch: Option<usize>) -> Outcome {
+        // the last directory that still needs the sums from before `t`
+        let mut needed_until = vec![None; until];
+        for i in 0..until {
+            needed_until[self.first[i]] = Some(i);
+        }
+
+        // only sums below `space_needed` are tracked
+        let mut reachable = Bitset::new(self.space_needed);
+        reachable.set(0);
+        let mut snapshots: HashMap<usize, Bitset> = HashMap::new();
+        let mut outcome = Outcome {
+            best: None,
+            reached_by: None,
+        };
+
+        for i in 0..until {
+            if needed_until[i].is_some() {
+                snapshots.insert(i, reachable.clone());
+            }
+
+            let size = self.sizes[i];
+            let outside = &snapshots[&self.first[i]];
+
+            // the smallest way to go over, anything reachable plus this one
+            let over = match self.space_needed.checked_sub(size) {
+                Some(from) if from > 0 => outside.first_set_from(from).map(|sum| (sum + size, sum)),
+                _ => Some((size, 0)),
+            };
+            if let Some((total, sum)) = over {
+                let better = match outcome.best {
+                    Some((best, _, _)) => total < best,
+                    None => true,
+                };
+                if better {
+                    outcome.best = Some((total, i, sum));
+                }
+            }
+
+            reachable.or_shifted(outside, size);
+
+            if needed_until[self.first[i]] == Some(i) {
+                snapshots.remove(&self.first[i]);
+            }
+
+            if let Some(watch) = watch {
+                if reachable.get(watch) {
+                    outcome.reached_by = Some(i);
+                    break;
+                }
+            } else if matches!(outcome.best, Some((total, _, _)) if total == self.space_needed) {
+                // can't do better than exactly enough
+                break;
+            }
+        }
+
+        outcome
+    }
+}
+
+/// fixed size set of `usize`s below `len`
+#[derive(Clone)]
+struct Bitset {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    fn new(len: usize) -> Bitset {
+        Bitset {
+            len,
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn get(&self, i: usize) -> bool {
+        i < self.len && self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn set(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    /// the smallest member that's at least `from`
+    fn first_set_from(&self, from: usize) -> Option<usize> {
+        if from >= self.len {
+            return None;
+        }
+
+        let mut w = from / 64;
+        let mut word = self.words[w] & (!0 << (from % 64));
+        loop {
+            if word != 0 {
+                return Some(w * 64 + word.trailing_zeros() as usize);
+            }
+            w += 1;
+            if w == self.words.len() {
+                return None;
+            }
+            word = self.words[w];
+        }
+    }
+
+    /// adds every member of `src` plus `shift`, dropping what falls outside
+    fn or_shifted(&mut self, src: &Bitset, shift: usize) {
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+
+        for w in (word_shift..self.words.len()).rev() {
+            let mut word = src.words[w - word_shift] << bit_shift;
+            if bit_shift > 0 && w > word_shift {
+                word |= src.words[w - word_shift - 1] >> (64 - bit_shift);
+            }
+            self.words[w] |= word;
+        }
+
+        let spare = self.len % 64;
+        if spare > 0 {
+            let last = self.words.len() - 1;
+            self.words[last] &= (1 << spare) - 1;
+        }
+    }
+}
+
+/// the directories directly inside `dir`, with their paths
+fn subdirs(dir: &Rc<RefCell<Directory>>, path: &str) -> Vec<(String, Rc<RefCell<Directory>>)> {
+    dir.borrow()
+        .children
+        .iter()
+        .filter_map(|node| match node {
+            Node::Dir(child) => Some((query::join(path, &child.borrow().name), Rc::clone(child))),
+            Node::Reg(_) => None,
+        })
+        .collect()
+}
+
+/// pushes `dir` and the directories inside it in post-order, along with the
+/// index each subtree starts at
+fn post_order(
+    dir: Rc<RefCell<Directory>>,
+    path: String,
+    dirs: &mut Vec<(String, Rc<RefCell<Directory>>)>,
+    first: &mut Vec<usize>,
+) {
+    let start = dirs.len();
+    for (child_path, child) in subdirs(&dir, &path) {
+        post_order(child, child_path, dirs, first);
+    }
+    dirs.push((path, dir));
+    first.push(start);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Plan, Planner};
+    use crate::parsing;
+
+    fn plan(dirs: &[&str], freed: usize) -> Option<Plan> {
+        Some(Plan {
+            dirs: dirs.iter().map(|dir| dir.to_string()).collect(),
+            freed,
+        })
+    }
+
+    #[test]
+    fn test_smallest_single() {
+        let root = parsing::parse_input("test_input.txt");
+
+        let planner = Planner::new(70000000, 30000000);
+        assert_eq!(planner.space_to_free(&root), 8381165);
+        assert_eq!(planner.smallest_single(&root), plan(&["/d"], 24933642));
+
+        let planner = Planner::new(48381165, 500);
+        assert_eq!(planner.smallest_single(&root), plan(&["/a/e"], 584));
+
+        let planner = Planner::new(100000000, 30000000);
+        assert_eq!(planner.smallest_single(&root), plan(&[], 0));
+
+        let planner = Planner::new(0, 30000000);
+        assert_eq!(planner.smallest_single(&root), None);
+    }
+
+    const TRANSCRIPT: &str = "\
+$ cd /
+$ ls
+dir x
+dir y
+dir z
+$ cd x
+$ ls
+600 x.bin
+$ cd ..
+$ cd y
+$ ls
+500 y.bin
+$ cd ..
+$ cd z
+$ ls
+dir w
+600 z.bin
+$ cd w
+$ ls
+400 w.bin
+";
+
+    #[test]
+    fn test_optimal() {
+        // 2100 used in total
+        let root = parsing::parse_str(TRANSCRIPT);
+
+        // no single directory is big enough, x + y is the best pair
+        let planner = Planner::new(3000, 1950);
+        assert_eq!(planner.space_to_free(&root), 1050);
+        assert_eq!(planner.smallest_single(&root), None);
+        assert_eq!(planner.optimal(&root), plan(&["/x", "/y"], 1100));
+
+        // z + w would be 1400, but w is inside z
+        let planner = Planner::new(2100, 1400);
+        assert_eq!(planner.optimal(&root).map(|plan| plan.freed), Some(1500));
+
+        let planner = Planner::new(3000, 1000);
+        assert_eq!(planner.optimal(&root), plan(&["/z/w"], 400));
+
+        let planner = Planner::new(2100, 2101);
+        assert_eq!(planner.optimal(&root), None);
+    }
+
+    #[test]
+    fn test_optimal_matches_single_on_puzzle() {
+        let root = parsing::parse_input("test_input.txt");
+        let planner = Planner::new(70000000, 30000000);
+
+        assert_eq!(planner.optimal(&root), planner.smallest_single(&root));
+    }
+}
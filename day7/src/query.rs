@@ -0,0 +1,247 @@
+//! Path based lookups on a parsed `Directory` tree.
+//!
+//! Paths look like the ones in the puzzle: `/a/e/i`. A leading `/` starts at
+//! the root, anything else is relative to the directory passed in. `.` and
+//! `..` are understood.
+
+use crate::{Directory, Node};
+use std::{cell::RefCell, rc::Rc};
+
+/// looks up the node at `path`, starting from `pwd`
+pub fn resolve(pwd: &Rc<RefCell<Directory>>, path: &str) -> Option<Node> {
+    let mut node = Node::Dir(if path.starts_with('/') {
+        root_of(pwd)
+    } else {
+        Rc::clone(pwd)
+    });
+
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        let dir = match &node {
+            Node::Dir(dir) => Rc::clone(dir),
+            // can't descend into a regular file
+            Node::Reg(_) => return None,
+        };
+
+        node = match name {
+            "." => continue,
+            ".." => Node::Dir(dir.borrow().parent.as_ref()?.upgrade()?),
+            name => dir.borrow().get_node(name)?.clone(),
+        };
+    }
+
+    Some(node)
+}
+
+/// total size of whatever `path` points to
+pub fn size_of(pwd: &Rc<RefCell<Directory>>, path: &str) -> Option<usize> {
+    resolve(pwd, path).map(|node| node.calc_size())
+}
+
+/// follows the parent links all the way up
+pub fn root_of(dir: &Rc<RefCell<Directory>>) -> Rc<RefCell<Directory>> {
+    let mut dir = Rc::clone(dir);
+    loop {
+        let parent = match &dir.borrow().parent {
+            Some(parent) => parent.upgrade().expect("should be available"),
+            None => break,
+        };
+        dir = parent;
+    }
+    dir
+}
+
+/// absolute path of `dir`, e.g. `/a/e`
+pub fn path_of(dir: &Rc<RefCell<Directory>>) -> String {
+    let mut names = vec![];
+    let mut dir = Rc::clone(dir);
+    loop {
+        let parent = match &dir.borrow().parent {
+            Some(parent) => parent.upgrade().expect("should be available"),
+            None => break,
+        };
+        names.push(dir.borrow().name.clone());
+        dir = parent;
+    }
+    names.reverse();
+    format!("/{}", names.join("/"))
+}
+
+/// joins a directory path and a child name without doubling the `/`
+pub fn join(path: &str, name: &str) -> String {
+    if path.ends_with('/') {
+        format!("{}{}", path, name)
+    } else {
+        format!("{}/{}", path, name)
+    }
+}
+
+/// Depth-first, pre-order iterator over a tree. See [`walk`].
+pub struct Walk {
+    stack: Vec<(String, Node)>,
+}
+
+impl Iterator for Walk {
+    type Item = (String, Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+
+        if let Node::Dir(dir) = &node {
+            // pushed in reverse so children come out in listing order
+            for child in dir.borrow().children.iter().rev() {
+                self.stack.push((join(&path, &child.name()), child.clone()));
+            }
+        }
+
+        Some((path, node))
+    }
+}
+
+/// walks `dir` and everything under it, yielding `(path, node)` with
+/// absolute paths. `dir` itself comes first.
+pub fn walk(dir: &Rc<RefCell<Directory>>) -> Walk {
+    Walk {
+        stack: vec![(path_of(dir), Node::Dir(Rc::clone(dir)))],
+    }
+}
+
+/// finds every node under `dir` matching `pattern`.
+///
+/// A pattern without a `/` is matched against the node's name only, so
+/// `*.dat` finds `.dat` files at any depth. A pattern with a `/` is matched
+/// against the whole path instead.
+pub fn find(dir: &Rc<RefCell<Directory>>, pattern: &str) -> Vec<(String, Node)> {
+    walk(dir)
+        .filter(|(path, node)| {
+            if pattern.contains('/') {
+                glob_match(pattern, path)
+            } else {
+                glob_match(pattern, &node.name())
+            }
+        })
+        .collect()
+}
+
+/// shell style matching, `*` matches any run of characters except `/` and
+/// `?` matches exactly one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // position to go back to when a literal fails to match after a `*`
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') if text[t] != '/' => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            Some(&c) if c != '?' && c == text[t] => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => (),
+        }
+
+        match backtrack {
+            // let the `*` eat one more character
+            Some((star, star_t)) if text[star_t] != '/' => {
+                backtrack = Some((star, star_t + 1));
+                p = star + 1;
+                t = star_t + 1;
+            }
+            _ => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, glob_match, resolve, size_of, walk};
+    use crate::{parsing, Node};
+
+    #[test]
+    fn test_resolve() {
+        let root = parsing::parse_input("test_input.txt");
+
+        match resolve(&root, "/a/e/i") {
+            Some(Node::Reg(file)) => assert_eq!(file.size, 584),
+            _ => panic!("/a/e/i should be a file"),
+        }
+        assert!(matches!(resolve(&root, "/a/e"), Some(Node::Dir(_))));
+        assert!(matches!(resolve(&root, "a/../d/./k"), Some(Node::Reg(_))));
+        assert!(resolve(&root, "/a/nope").is_none());
+        assert!(resolve(&root, "/b.txt/x").is_none());
+        assert!(resolve(&root, "..").is_none());
+    }
+
+    #[test]
+    fn test_size_of() {
+        let root = parsing::parse_input("test_input.txt");
+
+        assert_eq!(size_of(&root, "/"), Some(48381165));
+        assert_eq!(size_of(&root, "/a"), Some(94853));
+        assert_eq!(size_of(&root, "/d"), Some(24933642));
+        assert_eq!(size_of(&root, "/a/e"), Some(584));
+        assert_eq!(size_of(&root, "/c.dat"), Some(8504156));
+    }
+
+    #[test]
+    fn test_walk() {
+        let root = parsing::parse_input("test_input.txt");
+        let paths: Vec<String> = walk(&root).map(|(path, _)| path).collect();
+
+        assert_eq!(
+            paths,
+            [
+                "/", "/a", "/a/e", "/a/e/i", "/a/f", "/a/g", "/a/h.lst", "/b.txt", "/c.dat", "/d",
+                "/d/j", "/d/d.log", "/d/d.ext", "/d/k"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let root = parsing::parse_input("test_input.txt");
+        let paths = |pattern| -> Vec<String> {
+            find(&root, pattern)
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect()
+        };
+
+        assert_eq!(paths("*.dat"), ["/c.dat"]);
+        assert_eq!(paths("d.*"), ["/d/d.log", "/d/d.ext"]);
+        assert_eq!(paths("/a/*"), ["/a/e", "/a/f", "/a/g", "/a/h.lst"]);
+        assert_eq!(paths("/*/?"), ["/a/e", "/a/f", "/a/g", "/d/j", "/d/k"]);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        let tests = [
+            ("*", "anything", true),
+            ("*.dat", "c.dat", true),
+            ("*.dat", "c.dat.bak", false),
+            ("?.txt", "b.txt", true),
+            ("?.txt", "bb.txt", false),
+            ("/a/*", "/a/e/i", false),
+            ("/a/*/i", "/a/e/i", true),
+            ("*a*a*", "banana", true),
+        ];
+
+        for test in tests {
+            assert_eq!(glob_match(test.0, test.1), test.2, "{} {}", test.0, test.1);
+        }
+    }
+}
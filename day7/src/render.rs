@@ -0,0 +1,255 @@
+//! Text representations of a `Directory` tree: the puzzle's own listing
+//! format, a `du -h` style summary and JSON.
+
+use crate::{query, Directory, FileSize, Node};
+use std::{cell::RefCell, fmt::Write, rc::Rc};
+
+/// renders the tree the way the puzzle description shows it
+///
+/// ```text
+/// - / (dir)
+///   - a (dir)
+///     - i (file, size=584)
+/// ```
+pub fn tree(dir: &Rc<RefCell<Directory>>) -> String {
+    let mut out = String::new();
+    write_tree(&mut out, &Node::Dir(Rc::clone(dir)), 0);
+    out
+}
+
+fn write_tree(out: &mut String, node: &Node, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match node {
+        Node::Reg(file) => {
+            writeln!(out, "{}- {} (file, size={})", indent, file.name, file.size).unwrap();
+        }
+        Node::Dir(dir) => {
+            writeln!(out, "{}- {} (dir)", indent, dir.borrow().name).unwrap();
+            for child in dir.borrow().children.iter() {
+                write_tree(out, child, depth + 1);
+            }
+        }
+    }
+}
+
+/// cumulative size of every directory at most `max_depth` levels below
+/// `dir`, biggest first. Directories of equal size are ordered by path.
+pub fn du(dir: &Rc<RefCell<Directory>>, max_depth: Option<usize>) -> Vec<(usize, String)> {
+    let base_depth = depth_of(&query::path_of(dir));
+
+    let mut entries: Vec<(usize, String)> = query::walk(dir)
+        .filter_map(|(path, node)| match node {
+            Node::Dir(d) => Some((d.borrow().calculate_size(), path)),
+            Node::Reg(_) => None,
+        })
+        .filter(|(_, path)| match max_depth {
+            Some(max_depth) => depth_of(path) - base_depth <= max_depth,
+            None => true,
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    entries
+}
+
+fn depth_of(path: &str) -> usize {
+    path.split('/').filter(|name| !name.is_empty()).count()
+}
+
+/// formats the output of `du` one entry per line, `size<TAB>path`
+pub fn format_du(entries: &[(usize, String)], human_readable: bool) -> String {
+    let mut out = String::new();
+    for (size, path) in entries {
+        let size = if human_readable {
+            human_size(*size)
+        } else {
+            size.to_string()
+        };
+        writeln!(out, "{}\t{}", size, path).unwrap();
+    }
+    out
+}
+
+/// sizes like `du -h` prints them: powers of 1024, rounded up, with one
+/// decimal below 10
+pub fn human_size(size: usize) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+
+    if size < 1024 {
+        return size.to_string();
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    loop {
+        let rounded = if value < 10.0 {
+            (value * 10.0).ceil() / 10.0
+        } else {
+            value.ceil()
+        };
+        // rounding up can reach the next unit, 1023.5K is 1.0M
+        if rounded >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+            continue;
+        }
+
+        return if rounded < 10.0 {
+            format!("{:.1}{}", rounded, UNITS[unit])
+        } else {
+            format!("{}{}", rounded, UNITS[unit])
+        };
+    }
+}
+
+/// the whole tree as JSON. Directories carry their cumulative size.
+///
+/// `{"name":"/","type":"dir","size":48381165,"children":[...]}`
+pub fn json(dir: &Rc<RefCell<Directory>>) -> String {
+    let mut out = String::new();
+    write_json(&mut out, &Node::Dir(Rc::clone(dir)));
+    out
+}
+
+fn write_json(out: &mut String, node: &Node) {
+    match node {
+        Node::Reg(file) => {
+            write!(
+                out,
+                r#"{{"name":{},"type":"file","size":{}}}"#,
+                json_string(&file.name),
+                file.size
+            )
+            .unwrap();
+        }
+        Node::Dir(dir) => {
+            let dir = dir.borrow();
+            write!(
+                out,
+                r#"{{"name":{},"type":"dir","size":{},"children":["#,
+                json_string(&dir.name),
+                dir.calculate_size()
+            )
+            .unwrap();
+            for (i, child) in dir.children.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json(out, child);
+            }
+            out.push_str("]}");
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{du, format_du, human_size, json, json_string, tree};
+    use crate::{parsing, query, Node};
+
+    #[test]
+    fn test_tree() {
+        let root = parsing::parse_input("test_input.txt");
+        let expected = "\
+- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+";
+
+        assert_eq!(tree(&root), expected);
+    }
+
+    #[test]
+    fn test_du() {
+        let root = parsing::parse_input("test_input.txt");
+
+        assert_eq!(
+            format_du(&du(&root, None), false),
+            "48381165\t/\n24933642\t/d\n94853\t/a\n584\t/a/e\n"
+        );
+        assert_eq!(
+            format_du(&du(&root, Some(1)), true),
+            "47M\t/\n24M\t/d\n93K\t/a\n"
+        );
+
+        let a = match query::resolve(&root, "/a") {
+            Some(Node::Dir(a)) => a,
+            _ => panic!("/a should be a directory"),
+        };
+        assert_eq!(du(&a, Some(0)), [(94853, "/a".to_string())]);
+    }
+
+    #[test]
+    fn test_human_size() {
+        let tests = [
+            (0, "0"),
+            (1023, "1023"),
+            (1024, "1.0K"),
+            (1025, "1.1K"),
+            (10239, "10K"),
+            (94853, "93K"),
+            (1572864, "1.5M"),
+            (48381165, "47M"),
+            (1048064, "1.0M"),
+            (1048575, "1.0M"),
+            (1048576, "1.0M"),
+            (1047552, "1023K"),
+        ];
+
+        for test in tests {
+            assert_eq!(human_size(test.0), test.1);
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let root = parsing::parse_input("test_input.txt");
+        let a = match query::resolve(&root, "/a") {
+            Some(Node::Dir(a)) => a,
+            _ => panic!("/a should be a directory"),
+        };
+
+        assert_eq!(
+            json(&a),
+            concat!(
+                r#"{"name":"a","type":"dir","size":94853,"children":["#,
+                r#"{"name":"e","type":"dir","size":584,"children":["#,
+                r#"{"name":"i","type":"file","size":584}]},"#,
+                r#"{"name":"f","type":"file","size":29116},"#,
+                r#"{"name":"g","type":"file","size":2557},"#,
+                r#"{"name":"h.lst","type":"file","size":62596}]}"#,
+            )
+        );
+        // the root carries the total, as in the example above
+        assert!(json(&root).starts_with(r#"{"name":"/","type":"dir","size":48381165,"children":["#));
+        assert_eq!(json_string("a\"b\\c\n"), r#""a\"b\\c\n""#);
+    }
+}
@@ -0,0 +1,316 @@
+//! A small shell for poking around a parsed `Directory` tree.
+//!
+//! Sizes are always computed from what's currently in the tree, so after
+//! an `rm` every `du` and `df` already reflects the deletion.
+
+use crate::{planner::Planner, query, render, Directory, FileSize, Node};
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
+
+const HELP: &str = "\
+cd <path>               change directory
+ls [path]               list a directory like the puzzle's ls
+pwd                     print the current directory
+tree [path]             show everything below a directory
+du [-h] [depth]         cumulative directory sizes below the current directory
+find <pattern> [size]   find nodes matching a glob, at least `size` big
+rm <path>               delete a file or directory
+df                      used and free space, and how much still has to go
+help                    show this
+exit                    leave the shell
+";
+
+pub struct Shell {
+    root: Rc<RefCell<Directory>>,
+    pwd: Rc<RefCell<Directory>>,
+    /// the disk's size and how much must be free, for `df`
+    planner: Planner,
+    /// print a prompt before reading each command
+    pub prompt: bool,
+}
+
+impl Shell {
+    pub fn new(root: Rc<RefCell<Directory>>, planner: Planner) -> Shell {
+        Shell {
+            pwd: Rc::clone(&root),
+            root,
+            planner,
+            prompt: false,
+        }
+    }
+
+    /// reads commands from `input` until it runs out or `exit` is given
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if self.prompt {
+                write!(output, "{}$ ", query::path_of(&self.pwd))?;
+                output.flush()?;
+            }
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+
+            let args: Vec<&str> = line.split_whitespace().collect();
+            if let ["exit"] | ["quit"] = args[..] {
+                return Ok(());
+            }
+
+            let out = self.execute(&args);
+            output.write_all(out.as_bytes())?;
+        }
+    }
+
+    /// runs one command and returns what it printed
+    pub fn execute(&mut self, args: &[&str]) -> String {
+        match args {
+            [] => String::new(),
+            ["cd"] => {
+                self.pwd = Rc::clone(&self.root);
+                String::new()
+            }
+            ["cd", path] => match query::resolve(&self.pwd, path) {
+                Some(Node::Dir(dir)) => {
+                    self.pwd = dir;
+                    String::new()
+                }
+                Some(Node::Reg(_)) => format!("cd: {}: not a directory\n", path),
+                None => format!("cd: {}: no such file or directory\n", path),
+            },
+            ["ls"] => self.ls("."),
+            ["ls", path] => self.ls(path),
+            ["pwd"] => format!("{}\n", query::path_of(&self.pwd)),
+            ["tree"] => render::tree(&self.pwd),
+            ["tree", path] => match query::resolve(&self.pwd, path) {
+                Some(Node::Dir(dir)) => render::tree(&dir),
+                Some(Node::Reg(file)) => format!("- {} (file, size={})\n", file.name, file.size),
+                None => format!("tree: {}: no such file or directory\n", path),
+            },
+            ["du", opts @ ..] => {
+                let human_readable = opts.contains(&"-h");
+                let max_depth = match opts.iter().find(|&&opt| opt != "-h") {
+                    Some(depth) => match depth.parse() {
+                        Ok(depth) => Some(depth),
+                        Err(_) => return format!("du: {}: not a depth\n", depth),
+                    },
+                    None => None,
+                };
+                render::format_du(&render::du(&self.pwd, max_depth), human_readable)
+            }
+            ["find", pattern] => self.find(pattern, 0),
+            ["find", pattern, min_size] => match min_size.parse() {
+                Ok(min_size) => self.find(pattern, min_size),
+                Err(_) => format!("find: {}: not a size\n", min_size),
+            },
+            ["rm", path] => self.rm(path),
+            ["df"] => self.df(),
+            ["help"] => HELP.to_string(),
+            [cmd, ..] => format!("{}: command not found\n", cmd),
+        }
+    }
+
+    fn ls(&self, path: &str) -> String {
+        match query::resolve(&self.pwd, path) {
+            Some(Node::Dir(dir)) => dir
+                .borrow()
+                .children
+                .iter()
+                .map(|node| match node {
+                    Node::Dir(dir) => format!("dir {}\n", dir.borrow().name),
+                    Node::Reg(file) => format!("{} {}\n", file.size, file.name),
+                })
+                .collect(),
+            Some(Node::Reg(file)) => format!("{} {}\n", file.size, file.name),
+            None => format!("ls: {}: no such file or directory\n", path),
+        }
+    }
+
+    fn find(&self, pattern: &str, min_size: usize) -> String {
+        query::find(&self.pwd, pattern)
+            .into_iter()
+            .map(|(path, node)| (node.calc_size(), path))
+            .filter(|(size, _)| *size >= min_size)
+            .map(|(size, path)| format!("{} {}\n", size, path))
+            .collect()
+    }
+
+    fn rm(&mut self, path: &str) -> String {
+        let trimmed = path.trim_end_matches('/');
+        let (parent, name) = match trimmed.rsplit_once('/') {
+            Some(("", name)) => ("/", name),
+            Some((parent, name)) => (parent, name),
+            None => (".", trimmed),
+        };
+        if name.is_empty() || name == "." || name == ".." {
+            return format!("rm: {}: refusing to remove\n", path);
+        }
+
+        let parent = match query::resolve(&self.pwd, parent) {
+            Some(Node::Dir(dir)) => dir,
+            _ => return format!("rm: {}: no such file or directory\n", path),
+        };
+
+        if let Some(Node::Dir(dir)) = parent.borrow().get_node(name) {
+            if self.is_inside(dir) {
+                return format!("rm: {}: the current directory is inside it\n", path);
+            }
+        }
+
+        let removed = parent.borrow_mut().remove_child(name);
+        match removed {
+            Some(_) => String::new(),
+            None => format!("rm: {}: no such file or directory\n", path),
+        }
+    }
+
+    /// whether the current directory is `dir` or somewhere below it
+    fn is_inside(&self, dir: &Rc<RefCell<Directory>>) -> bool {
+        let mut curr = Some(Rc::clone(&self.pwd));
+        while let Some(d) = curr {
+            if Rc::ptr_eq(&d, dir) {
+                return true;
+            }
+            curr = d
+                .borrow()
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.upgrade());
+        }
+        false
+    }
+
+    fn df(&self) -> String {
+        let used = self.root.borrow().calculate_size();
+        let capacity = self.planner.capacity();
+        format!(
+            "total {}\nused {}\nfree {}\nstill needed {}\n",
+            capacity,
+            used,
+            capacity.saturating_sub(used),
+            self.planner.space_to_free(&self.root)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shell;
+    use crate::{parsing, planner::Planner, FS_MAX, SPACE_NEEDED};
+
+    fn run_script(script: &str) -> String {
+        run_script_on(script, Planner::new(FS_MAX, SPACE_NEEDED))
+    }
+
+    fn run_script_on(script: &str, planner: Planner) -> String {
+        let mut shell = Shell::new(parsing::parse_input("test_input.txt"), planner);
+        let mut output = vec![];
+        shell.run(script.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_navigation() {
+        let output = run_script(
+            "\
+pwd
+cd a/e
+pwd
+ls
+cd ../..
+ls d
+cd /nope
+cd /b.txt
+cd
+pwd
+",
+        );
+
+        assert_eq!(
+            output,
+            "\
+/
+/a/e
+584 i
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+cd: /nope: no such file or directory
+cd: /b.txt: not a directory
+/
+"
+        );
+    }
+
+    #[test]
+    fn test_rm_updates_sizes() {
+        let output = run_script(
+            "\
+du
+rm a/e/i
+du
+rm /d
+df
+find *.lst
+",
+        );
+
+        assert_eq!(
+            output,
+            "\
+48381165\t/
+24933642\t/d
+94853\t/a
+584\t/a/e
+48380581\t/
+24933642\t/d
+94269\t/a
+0\t/a/e
+total 70000000
+used 23446939
+free 46553061
+still needed 0
+62596 /a/h.lst
+"
+        );
+    }
+
+    #[test]
+    fn test_df_uses_the_planners_disk() {
+        let output = run_script_on("df\n", Planner::new(50000000, 5000000));
+
+        assert_eq!(
+            output,
+            "total 50000000\nused 48381165\nfree 1618835\nstill needed 3381165\n"
+        );
+    }
+
+    #[test]
+    fn test_rm_trailing_slash() {
+        let output = run_script("rm a/\nrm d//\nls\n");
+        assert_eq!(output, "14848514 b.txt\n8504156 c.dat\n");
+    }
+
+    #[test]
+    fn test_rm_refuses_current_directory() {
+        let output = run_script("cd /a/e\nrm /a\nrm .\nrm i\nls\nexit\npwd\n");
+
+        assert_eq!(
+            output,
+            "rm: /a: the current directory is inside it\nrm: .: refusing to remove\n"
+        );
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        assert_eq!(
+            run_script("frobnicate x\n"),
+            "frobnicate: command not found\n"
+        );
+    }
+}
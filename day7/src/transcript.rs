@@ -0,0 +1,508 @@
+//! Making up terminal sessions to test against, and checking that a session
+//! could actually have happened.
+
+use crate::{
+    find_smallish_dirs,
+    parsing::{change_dir, record_dir},
+    planner::Planner,
+    Directory, FileSize, Node, FS_MAX, SPACE_NEEDED,
+};
+use std::{cell::RefCell, collections::HashSet, fmt::Display, rc::Rc};
+
+/// the biggest file `generate` makes, small enough that no tree it can
+/// hold in memory adds up to more than a usize
+pub const MAX_FILE_SIZE: usize = u32::MAX as usize;
+
+/// how file sizes are picked
+pub enum SizeDistribution {
+    Uniform {
+        min: usize,
+        max: usize,
+    },
+    /// lots of small files and a few big ones, like a real disk
+    LogUniform {
+        min: usize,
+        max: usize,
+    },
+}
+
+impl SizeDistribution {
+    /// `kind` is `uniform` or `log`, sizes go from `min` to `max` included,
+    /// which is at most `MAX_FILE_SIZE`
+    pub fn new(kind: &str, min: usize, max: usize) -> Result<SizeDistribution, String> {
+        if min > max {
+            return Err(format!(
+                "the smallest size {} is over the largest {}",
+                min, max
+            ));
+        }
+        if max > MAX_FILE_SIZE {
+            return Err(format!(
+                "files can be up to {} in size, not {}",
+                MAX_FILE_SIZE, max
+            ));
+        }
+        match kind {
+            "uniform" => Ok(SizeDistribution::Uniform { min, max }),
+            "log" => Ok(SizeDistribution::LogUniform { min, max }),
+            _ => Err(format!(
+                "{:?} isn't a size distribution, try uniform or log",
+                kind
+            )),
+        }
+    }
+
+    fn range(&self) -> (usize, usize) {
+        match *self {
+            SizeDistribution::Uniform { min, max } | SizeDistribution::LogUniform { min, max } => {
+                (min, max)
+            }
+        }
+    }
+}
+
+pub struct GenConfig {
+    /// how many levels of directories there are below the root
+    pub depth: usize,
+    /// the most directories a single directory can hold
+    pub fan_out: usize,
+    /// the most regular files a single directory can hold
+    pub max_files: usize,
+    pub sizes: SizeDistribution,
+    pub seed: u64,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        GenConfig {
+            depth: 4,
+            fan_out: 3,
+            max_files: 4,
+            sizes: SizeDistribution::LogUniform {
+                min: 1000,
+                max: 300000,
+            },
+            seed: 1,
+        }
+    }
+}
+
+/// a transcript together with the answers to both parts of the puzzle
+pub struct Generated {
+    pub transcript: String,
+    pub small_dirs_total: usize,
+    pub smallest_big_enough: Option<usize>,
+}
+
+/// xorshift64*, good enough for making up directory trees
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // the state must never be 0
+        Rng(seed ^ 0x9e3779b97f4a7c15 | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// uniform in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// uniform in `[0, 1)`
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct Generator<'a> {
+    config: &'a GenConfig,
+    rng: Rng,
+    out: String,
+}
+
+impl Generator<'_> {
+    fn size(&mut self) -> usize {
+        match self.config.sizes {
+            SizeDistribution::Uniform { min, max } => min + self.rng.below(max - min + 1),
+            SizeDistribution::LogUniform { min, max } => {
+                let (lo, hi) = ((min.max(1) as f64).ln(), (max.max(1) as f64).ln());
+                ((lo + (hi - lo) * self.rng.unit()).exp().round() as usize).clamp(min, max)
+            }
+        }
+    }
+
+    /// a name that isn't in `taken` yet
+    fn name(&mut self, taken: &mut HashSet<String>, extension: bool) -> String {
+        loop {
+            let len = 1 + self.rng.below(8);
+            let mut name: String = (0..len)
+                .map(|_| (b'a' + self.rng.below(26) as u8) as char)
+                .collect();
+            if extension && self.rng.below(2) == 0 {
+                name.push('.');
+                name.push_str(["txt", "dat", "log", "lst", "ext"][self.rng.below(5)]);
+            }
+            if taken.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+
+    fn fill(&mut self, pwd: Rc<RefCell<Directory>>, depth: usize) {
+        let n_dirs = if depth < self.config.depth {
+            self.rng.below(self.config.fan_out + 1)
+        } else {
+            0
+        };
+        let n_files = self.rng.below(self.config.max_files + 1);
+
+        let mut taken = HashSet::new();
+        let dirs: Vec<String> = (0..n_dirs).map(|_| self.name(&mut taken, false)).collect();
+        let mut listing: Vec<String> = dirs.iter().map(|name| format!("dir {}", name)).collect();
+        for _ in 0..n_files {
+            let name = self.name(&mut taken, true);
+            let size = self.size();
+            listing.push(format!("{} {}", size, name));
+        }
+
+        // ls doesn't put directories first
+        for i in (1..listing.len()).rev() {
+            listing.swap(i, self.rng.below(i + 1));
+        }
+
+        self.out.push_str("$ ls\n");
+        for line in listing {
+            record_dir(pwd.clone(), &line);
+            self.out.push_str(&line);
+            self.out.push('\n');
+        }
+
+        for name in dirs {
+            self.out.push_str(&format!("$ cd {}\n", name));
+            let child = change_dir(pwd.clone(), &name);
+            self.fill(child, depth + 1);
+            self.out.push_str("$ cd ..\n");
+        }
+    }
+}
+
+/// makes up a random transcript that explores every directory exactly once
+pub fn generate(config: &GenConfig) -> Generated {
+    let (min, max) = config.sizes.range();
+    assert!(min <= max, "the smallest file size is over the largest");
+    assert!(max <= MAX_FILE_SIZE, "files can't be that big");
+
+    let root = Rc::new(RefCell::new(Directory {
+        name: "/".to_string(),
+        parent: None,
+        children: vec![],
+    }));
+
+    let mut generator = Generator {
+        config,
+        rng: Rng::new(config.seed),
+        out: String::from("$ cd /\n"),
+    };
+    generator.fill(root.clone(), 0);
+
+    let small_dirs_total = find_smallish_dirs(root.clone())
+        .iter()
+        .map(|dir| dir.borrow().calculate_size())
+        .sum();
+    let smallest_big_enough = Planner::new(FS_MAX, SPACE_NEEDED)
+        .smallest_single(&root)
+        .map(|plan| plan.freed);
+
+    Generated {
+        transcript: generator.out,
+        small_dirs_total,
+        smallest_big_enough,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// a file was listed again with a different size
+    ConflictingSize {
+        name: String,
+        first: usize,
+        second: usize,
+    },
+    /// `cd` into something that `ls` never showed
+    NeverListed { name: String },
+    /// the same name is both a directory and a regular file
+    DirAndFile { name: String },
+    /// `cd ..` while already at `/`
+    AboveRoot,
+    /// not a command and not `ls` output
+    Malformed,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Issue {
+    /// 1-indexed
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.problem {
+            Problem::ConflictingSize {
+                name,
+                first,
+                second,
+            } => write!(f, "{} listed as {} and as {}", name, first, second),
+            Problem::NeverListed { name } => write!(f, "cd into {} which was never listed", name),
+            Problem::DirAndFile { name } => write!(f, "{} is both a dir and a file", name),
+            Problem::AboveRoot => write!(f, "cd .. from /"),
+            Problem::Malformed => write!(f, "can't make sense of this line"),
+        }
+    }
+}
+
+/// replays `transcript` and reports everything that couldn't have come
+/// from a real filesystem. Checking carries on after a problem, staying in
+/// the current directory when a `cd` fails.
+pub fn check(transcript: &str) -> Vec<Issue> {
+    let root = Rc::new(RefCell::new(Directory {
+        name: "/".to_string(),
+        parent: None,
+        children: vec![],
+    }));
+    let mut pwd = Rc::clone(&root);
+    let mut issues = vec![];
+
+    for (i, line) in transcript.lines().enumerate() {
+        let tokens: Vec<&str> = line.splitn(3, ' ').collect();
+
+        let problem = match tokens[..] {
+            ["$", "ls"] => None,
+            ["$", "cd", "/"] => {
+                pwd = change_dir(pwd, "/");
+                None
+            }
+            ["$", "cd", ".."] => {
+                if pwd.borrow().parent.is_none() {
+                    Some(Problem::AboveRoot)
+                } else {
+                    pwd = change_dir(pwd, "..");
+                    None
+                }
+            }
+            ["$", "cd", name] => {
+                let child = pwd.borrow().get_node(name).cloned();
+                match child {
+                    Some(Node::Dir(dir)) => {
+                        pwd = dir;
+                        None
+                    }
+                    Some(Node::Reg(_)) => Some(Problem::DirAndFile {
+                        name: name.to_string(),
+                    }),
+                    None => Some(Problem::NeverListed {
+                        name: name.to_string(),
+                    }),
+                }
+            }
+            ["$", ..] => Some(Problem::Malformed),
+            _ => match line.split_once(' ') {
+                Some((size_or_dir, name)) => check_listing(&pwd, line, size_or_dir, name),
+                None => Some(Problem::Malformed),
+            },
+        };
+
+        if let Some(problem) = problem {
+            issues.push(Issue {
+                line: i + 1,
+                problem,
+            });
+        }
+    }
+
+    issues
+}
+
+fn check_listing(
+    pwd: &Rc<RefCell<Directory>>,
+    line: &str,
+    size_or_dir: &str,
+    name: &str,
+) -> Option<Problem> {
+    let size = match size_or_dir {
+        "dir" => None,
+        sz => match sz.parse::<usize>() {
+            Ok(size) => Some(size),
+            Err(_) => return Some(Problem::Malformed),
+        },
+    };
+
+    let existing = pwd.borrow().get_node(name).cloned();
+    match (existing, size) {
+        (None, _) => {
+            record_dir(pwd.clone(), line);
+            None
+        }
+        (Some(Node::Dir(_)), None) => None,
+        (Some(Node::Reg(file)), Some(size)) if file.size == size => None,
+        (Some(Node::Reg(file)), Some(size)) => Some(Problem::ConflictingSize {
+            name: name.to_string(),
+            first: file.size,
+            second: size,
+        }),
+        _ => Some(Problem::DirAndFile {
+            name: name.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, generate, GenConfig, Issue, Problem, SizeDistribution, MAX_FILE_SIZE};
+    use crate::{find_smallish_dirs, parsing, planner::Planner, FileSize, FS_MAX, SPACE_NEEDED};
+    use std::fs;
+
+    #[test]
+    fn test_generate() {
+        for seed in 0..20 {
+            let config = GenConfig {
+                seed,
+                ..GenConfig::default()
+            };
+            let generated = generate(&config);
+            assert_eq!(check(&generated.transcript), []);
+
+            let root = parsing::parse_str(&generated.transcript);
+            let small_dirs_total: usize = find_smallish_dirs(root.clone())
+                .iter()
+                .map(|dir| dir.borrow().calculate_size())
+                .sum();
+            assert_eq!(small_dirs_total, generated.small_dirs_total);
+            assert_eq!(
+                Planner::new(FS_MAX, SPACE_NEEDED)
+                    .smallest_single(&root)
+                    .map(|plan| plan.freed),
+                generated.smallest_big_enough
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_respects_config() {
+        let config = GenConfig {
+            depth: 2,
+            fan_out: 2,
+            max_files: 3,
+            sizes: SizeDistribution::new("uniform", 10, 20).unwrap(),
+            seed: 7,
+        };
+        let transcript = generate(&config).transcript;
+
+        // the root is level 0, `/` plus two more levels
+        let mut depth = 0;
+        for line in transcript.lines() {
+            match line {
+                "$ cd /" => depth = 0,
+                "$ cd .." => depth -= 1,
+                line if line.starts_with("$ cd ") => depth += 1,
+                line if line.starts_with('$') || line.starts_with("dir ") => (),
+                line => {
+                    let size: usize = line.split(' ').next().unwrap().parse().unwrap();
+                    assert!((10..=20).contains(&size));
+                }
+            }
+            assert!(depth <= 2);
+        }
+        assert_eq!(generate(&config).transcript, transcript);
+    }
+
+    #[test]
+    fn test_size_distribution() {
+        assert!(SizeDistribution::new("log", 5, 5).is_ok());
+        assert_eq!(
+            SizeDistribution::new("uniform", 20, 10).err().unwrap(),
+            "the smallest size 20 is over the largest 10"
+        );
+        assert_eq!(
+            SizeDistribution::new("normal", 1, 10).err().unwrap(),
+            "\"normal\" isn't a size distribution, try uniform or log"
+        );
+        assert!(SizeDistribution::new("uniform", 0, MAX_FILE_SIZE).is_ok());
+        assert_eq!(
+            SizeDistribution::new("uniform", 0, usize::MAX)
+                .err()
+                .unwrap(),
+            format!("files can be up to 4294967295 in size, not {}", usize::MAX)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the smallest file size is over the largest")]
+    fn test_generate_rejects_bad_sizes() {
+        generate(&GenConfig {
+            sizes: SizeDistribution::Uniform { min: 20, max: 10 },
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check(&fs::read_to_string("test_input.txt").unwrap()), []);
+
+        let transcript = "\
+$ cd /
+$ ls
+dir a
+100 b
+$ ls
+200 b
+$ cd c
+$ cd b
+dir b
+$ cd ..
+what
+";
+        let issue = |line, problem| Issue { line, problem };
+        assert_eq!(
+            check(transcript),
+            [
+                issue(
+                    6,
+                    Problem::ConflictingSize {
+                        name: "b".to_string(),
+                        first: 100,
+                        second: 200
+                    }
+                ),
+                issue(
+                    7,
+                    Problem::NeverListed {
+                        name: "c".to_string()
+                    }
+                ),
+                issue(
+                    8,
+                    Problem::DirAndFile {
+                        name: "b".to_string()
+                    }
+                ),
+                issue(
+                    9,
+                    Problem::DirAndFile {
+                        name: "b".to_string()
+                    }
+                ),
+                issue(10, Problem::AboveRoot),
+                issue(11, Problem::Malformed),
+            ]
+        );
+    }
+}
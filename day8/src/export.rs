@@ -0,0 +1,92 @@
+//! Writing per-tree maps out for other tools: CSV, and PGM/PPM images that
+//! any image viewer can open.
+
+use std::fmt::{Display, Write};
+
+/// one line per row, values separated by commas
+pub fn to_csv<T: Display>(rows: &[Vec<T>]) -> String {
+    let mut out = String::new();
+    for row in rows {
+        for (i, value) in row.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(out, "{}", value).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// where `value` sits between 0 and `max`, as a byte
+fn scale(value: u64, max: u64) -> u8 {
+    if max == 0 {
+        return 0;
+    }
+    (value as u128 * 255 / max as u128) as u8
+}
+
+/// binary greyscale image, one pixel per tree. The biggest value is white.
+pub fn to_pgm(values: &[Vec<u64>]) -> Vec<u8> {
+    let (h, w) = (values.len(), values.first().map_or(0, Vec::len));
+    let max = values.iter().flatten().copied().max().unwrap_or(0);
+
+    let mut out = format!("P5\n{} {}\n255\n", w, h).into_bytes();
+    out.extend(values.iter().flatten().map(|&value| scale(value, max)));
+    out
+}
+
+/// binary colour image, one pixel per tree, going from blue for the
+/// smallest values through green to red for the biggest
+pub fn to_ppm_heatmap(values: &[Vec<u64>]) -> Vec<u8> {
+    let (h, w) = (values.len(), values.first().map_or(0, Vec::len));
+    let max = values.iter().flatten().copied().max().unwrap_or(0);
+
+    let mut out = format!("P6\n{} {}\n255\n", w, h).into_bytes();
+    for &value in values.iter().flatten() {
+        out.extend(heat(scale(value, max)));
+    }
+    out
+}
+
+fn heat(level: u8) -> [u8; 3] {
+    let t = level as f64 / 255.0;
+    // each channel peaks at a different point along the way
+    let channel = |peak: f64| ((1.5 - (4.0 * t - peak).abs()).clamp(0.0, 1.0) * 255.0) as u8;
+    [channel(3.0), channel(2.0), channel(1.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{heat, to_csv, to_pgm, to_ppm_heatmap};
+
+    #[test]
+    fn test_to_csv() {
+        assert_eq!(to_csv(&[vec![1, 2, 3], vec![4, 5, 6]]), "1,2,3\n4,5,6\n");
+        assert_eq!(to_csv(&[vec!["UL", "-"]]), "UL,-\n");
+    }
+
+    #[test]
+    fn test_to_pgm() {
+        let mut expected = b"P5\n3 2\n255\n".to_vec();
+        expected.extend([0, 51, 255, 127, 0, 0]);
+
+        assert_eq!(to_pgm(&[vec![0, 2, 10], vec![5, 0, 0]]), expected);
+        assert_eq!(to_pgm(&[vec![0, 0]]), b"P5\n2 1\n255\n\0\0");
+    }
+
+    #[test]
+    fn test_to_ppm_heatmap() {
+        let image = to_ppm_heatmap(&[vec![0, 4, 8]]);
+        let header = b"P6\n3 1\n255\n";
+
+        assert_eq!(&image[..header.len()], header);
+        assert_eq!(image.len(), header.len() + 9);
+        assert_eq!(heat(0), [0, 0, 127]);
+        assert_eq!(heat(255), [127, 0, 0]);
+        assert_eq!(
+            &image[header.len()..],
+            [heat(0), heat(127), heat(255)].concat()
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! A forest whose trees can grow or be cut down, keeping the puzzle's
+//! answers up to date without surveying everything again.
+//!
+//! Changing one tree only changes what's seen along the lines through it,
+//! its row and column when looking the puzzle's way, so only those are
+//! looked along again.
+
+use crate::{look_along_line, step_pairs, Grid, Step, VisibleFrom};
+use std::collections::BTreeMap;
+
+pub struct Forest {
+    grid: Grid,
+    steps: Vec<Step>,
+    pairs: Vec<(usize, Option<usize>)>,
+    width: usize,
+    /// how far every tree sees along every step, `steps.len()` per tree
+    distances: Vec<usize>,
+    visible_from: Vec<VisibleFrom>,
+    scores: Vec<u64>,
+    visible: usize,
+    /// how many trees have each score, to find the highest quickly
+    score_counts: BTreeMap<u64, usize>,
+    stack: Vec<(usize, usize, usize)>,
+}
+
+impl Forest {
+    /// surveys `grid` looking along every step in `steps`, at most 32
+    pub fn new(grid: Grid, steps: &[Step]) -> Forest {
+        assert!(steps.len() <= 32, "at most 32 directions are supported");
+
+        let width = grid[0].len();
+        let cells = width * grid.len();
+        let mut forest = Forest {
+            pairs: step_pairs(steps),
+            steps: steps.to_vec(),
+            width,
+            distances: vec![0; cells * steps.len()],
+            visible_from: vec![VisibleFrom::default(); cells],
+            scores: vec![1; cells],
+            visible: 0,
+            score_counts: BTreeMap::new(),
+            stack: vec![],
+            grid,
+        };
+
+        let (h, w) = (forest.grid.len() as isize, width as isize);
+        for pair in forest.pairs.clone() {
+            let step = forest.steps[pair.0];
+            for y in 0..h {
+                for x in 0..w {
+                    if forest.is_inside(x - step.0, y - step.1) {
+                        continue;
+                    }
+                    forest.look_along(pair, (x as usize, y as usize));
+                }
+            }
+        }
+
+        for cell in 0..cells {
+            forest.add_up(cell);
+        }
+        forest
+    }
+
+    pub fn height(&self, x: usize, y: usize) -> u8 {
+        self.grid[y][x]
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible
+    }
+
+    pub fn highest_score(&self) -> u64 {
+        self.score_counts.keys().next_back().copied().unwrap_or(0)
+    }
+
+    pub fn score(&self, x: usize, y: usize) -> u64 {
+        self.scores[y * self.width + x]
+    }
+
+    pub fn visible_from(&self, x: usize, y: usize) -> VisibleFrom {
+        self.visible_from[y * self.width + x]
+    }
+
+    /// changes the height of one tree and looks again along every line
+    /// through it
+    pub fn set(&mut self, x: usize, y: usize, height: u8) {
+        if self.grid[y][x] == height {
+            return;
+        }
+
+        // a step that's a multiple of another covers some trees twice
+        let mut starts = vec![];
+        let mut touched = vec![];
+        for &pair in &self.pairs {
+            let start = self.line_start((x, y), self.steps[pair.0]);
+            touched.extend(self.line(start, self.steps[pair.0]));
+            starts.push((pair, start));
+        }
+        touched.sort_unstable();
+        touched.dedup();
+
+        for &cell in &touched {
+            self.take_away(cell);
+        }
+        self.grid[y][x] = height;
+        for (pair, start) in starts {
+            self.look_along(pair, start);
+        }
+        for &cell in &touched {
+            self.add_up(cell);
+        }
+    }
+
+    fn is_inside(&self, x: isize, y: isize) -> bool {
+        (0..self.width as isize).contains(&x) && (0..self.grid.len() as isize).contains(&y)
+    }
+
+    /// the first tree of the line along `step` that goes through `cell`
+    fn line_start(&self, cell: (usize, usize), step: Step) -> (usize, usize) {
+        let (mut x, mut y) = (cell.0 as isize, cell.1 as isize);
+        while self.is_inside(x - step.0, y - step.1) {
+            x -= step.0;
+            y -= step.1;
+        }
+        (x as usize, y as usize)
+    }
+
+    /// indices of every tree on the line from `start` along `step`
+    fn line(&self, start: (usize, usize), step: Step) -> Vec<usize> {
+        let (mut x, mut y) = (start.0 as isize, start.1 as isize);
+        let mut cells = vec![];
+        while self.is_inside(x, y) {
+            cells.push(y as usize * self.width + x as usize);
+            x += step.0;
+            y += step.1;
+        }
+        cells
+    }
+
+    /// looks both ways along the line from `start`, recording what's seen
+    fn look_along(&mut self, (i, opposite): (usize, Option<usize>), start: (usize, usize)) {
+        let n = self.steps.len();
+        let (width, distances, visible_from) =
+            (self.width, &mut self.distances, &mut self.visible_from);
+
+        look_along_line(
+            &self.grid,
+            start,
+            self.steps[i],
+            (opposite, Some(i)),
+            &mut self.stack,
+            &mut |x, y, dir, distance, seen_from_edge| {
+                let cell = y * width + x;
+                distances[cell * n + dir] = distance;
+                visible_from[cell].set(dir, seen_from_edge);
+            },
+        );
+    }
+
+    /// works out the score of `cell` and counts it in the totals
+    fn add_up(&mut self, cell: usize) {
+        let n = self.steps.len();
+        let score = self.distances[cell * n..(cell + 1) * n]
+            .iter()
+            .fold(1u64, |score, &distance| {
+                score.saturating_mul(distance as u64)
+            });
+
+        self.scores[cell] = score;
+        *self.score_counts.entry(score).or_insert(0) += 1;
+        self.visible += self.visible_from[cell].is_visible() as usize;
+    }
+
+    /// the opposite of `add_up`, before `cell` changes
+    fn take_away(&mut self, cell: usize) {
+        let score = self.scores[cell];
+        let count = self
+            .score_counts
+            .get_mut(&score)
+            .expect("every score is counted");
+        *count -= 1;
+        if *count == 0 {
+            self.score_counts.remove(&score);
+        }
+        self.visible -= self.visible_from[cell].is_visible() as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Forest;
+    use crate::{generate_forest, survey, Grid, Step, EIGHT_WAY, FOUR_WAY};
+
+    fn assert_matches_survey(forest: &Forest, grid: &Grid, steps: &[Step]) {
+        let survey = survey(grid, steps);
+        assert_eq!(forest.visible_count(), survey.visible_count());
+        assert_eq!(forest.highest_score(), survey.highest_score());
+
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &height) in row.iter().enumerate() {
+                assert_eq!(forest.height(x, y), height);
+                assert_eq!(forest.score(x, y), survey.scores[y * row.len() + x]);
+                assert_eq!(
+                    forest.visible_from(x, y),
+                    survey.visible_from[y * row.len() + x]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_example_update() {
+        let mut grid = vec![
+            vec![3, 0, 3, 7, 3],
+            vec![2, 5, 5, 1, 2],
+            vec![6, 5, 3, 3, 2],
+            vec![3, 3, 5, 4, 9],
+            vec![3, 5, 3, 9, 0],
+        ];
+        let mut forest = Forest::new(grid.clone(), &FOUR_WAY);
+        assert_eq!((forest.visible_count(), forest.highest_score()), (21, 8));
+
+        // the hidden 1 grows taller than everything around it
+        forest.set(3, 1, 8);
+        grid[1][3] = 8;
+        assert_matches_survey(&forest, &grid, &FOUR_WAY);
+        assert_eq!(forest.visible_count(), 22);
+    }
+
+    #[test]
+    fn test_random_updates_match_survey() {
+        // a step with a multiple in the set covers some lines twice
+        let odd_steps = [(1, 0), (2, 0), (1, 2), (-1, -2)];
+
+        for seed in 0..10 {
+            let (w, h) = (3 + seed as usize % 6, 2 + seed as usize % 5);
+            for steps in [&FOUR_WAY[..], &EIGHT_WAY[..], &odd_steps[..]] {
+                let mut grid = generate_forest(w, h, seed);
+                let mut forest = Forest::new(grid.clone(), steps);
+                assert_matches_survey(&forest, &grid, steps);
+
+                let changes = generate_forest(3, 40, seed + 100);
+                for change in changes {
+                    let (x, y) = (change[0] as usize % w, change[1] as usize % h);
+                    forest.set(x, y, change[2]);
+                    grid[y][x] = change[2];
+                    assert_matches_survey(&forest, &grid, steps);
+                }
+            }
+        }
+    }
+}
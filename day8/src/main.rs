@@ -1,153 +1,712 @@
-use std::{
-    fs::File,
-    io::{self, BufRead},
-};
+use std::{env, fs, io, path::Path, process, time::Instant};
 
-const ORD_ZERO: u8 = 48;
+mod export;
+mod forest;
+mod parsing;
+mod query;
+mod viewshed;
 
 type Grid = Vec<Vec<u8>>;
 
+/// one step along a line of sight as (dx, dy), y grows downwards
+type Step = (isize, isize);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum Direction {
     Up,
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 use Direction::*;
 
-// returns (x, y)
-fn get_tree_pos_across_axis(from: &Direction, grid: &Grid) -> (Vec<usize>, Vec<usize>) {
-    let h = grid.len();
-    let w = grid[0].len();
+impl Direction {
+    const ALL: [Direction; 8] = [Up, Down, Left, Right, UpLeft, UpRight, DownLeft, DownRight];
+
+    fn step(&self) -> Step {
+        match self {
+            Up => (0, -1),
+            Down => (0, 1),
+            Left => (-1, 0),
+            Right => (1, 0),
+            UpLeft => (-1, -1),
+            UpRight => (1, -1),
+            DownLeft => (-1, 1),
+            DownRight => (1, 1),
+        }
+    }
+
+    fn abbreviation(&self) -> &'static str {
+        match self {
+            Up => "U",
+            Down => "D",
+            Left => "L",
+            Right => "R",
+            UpLeft => "UL",
+            UpRight => "UR",
+            DownLeft => "DL",
+            DownRight => "DR",
+        }
+    }
+}
 
-    match from {
-        Left | Up => ((0..w).collect(), (0..h).collect()),
-        Right => ((0..w).rev().collect(), (0..h).collect()),
-        Down => ((0..w).collect(), (0..h).rev().collect()),
+/// the puzzle's way of looking, along rows and columns
+const FOUR_WAY: [Step; 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+/// rows, columns and both diagonals
+const EIGHT_WAY: [Step; 8] = [
+    (0, -1),
+    (0, 1),
+    (-1, 0),
+    (1, 0),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+    (1, 1),
+];
+
+/// `U`, `DR` etc. for the named directions, `(dx dy)` for anything else
+fn step_name(step: Step) -> String {
+    match Direction::ALL.iter().find(|dir| dir.step() == step) {
+        Some(dir) => dir.abbreviation().to_string(),
+        None => format!("({} {})", step.0, step.1),
     }
 }
 
-fn look_horizontal(grid: &Grid, map: &mut Vec<Vec<bool>>, xs: &[usize], ys: &[usize]) {
-    for y in ys.iter().skip(1) {
-        let mut curr_highest = grid[*y][xs[0]];
-        for x in xs.iter().skip(1) {
-            let height = grid[*y][*x];
-            if height > curr_highest {
-                curr_highest = height;
-                map[*y][*x] = true;
-            }
+/// The edges a tree can be seen from, as a set of indices into the steps
+/// the `Survey` was made with. Looking along step `s` means the tree is
+/// visible from the edge in that direction.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+struct VisibleFrom(u32);
+
+impl VisibleFrom {
+    fn add(&mut self, index: usize) {
+        self.0 |= 1 << index;
+    }
+
+    fn set(&mut self, index: usize, visible: bool) {
+        if visible {
+            self.add(index);
+        } else {
+            self.0 &= !(1 << index);
         }
     }
+
+    fn contains(&self, index: usize) -> bool {
+        self.0 & (1 << index) != 0
+    }
+
+    fn is_visible(&self) -> bool {
+        self.0 != 0
+    }
+
+    fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// the steps out of `steps` the tree is visible along
+    fn steps(&self, steps: &[Step]) -> Vec<Step> {
+        (0..steps.len())
+            .filter(|&i| self.contains(i))
+            .map(|i| steps[i])
+            .collect()
+    }
+
+    /// e.g. `UL` for up and left, `-` when hidden. Names are joined with `+`
+    /// unless they're all single letters.
+    fn describe(&self, steps: &[Step]) -> String {
+        let names: Vec<String> = self.steps(steps).into_iter().map(step_name).collect();
+        if names.is_empty() {
+            "-".to_string()
+        } else if names.iter().all(|name| name.len() == 1) {
+            names.concat()
+        } else {
+            names.join("+")
+        }
+    }
+}
+
+/// What every tree sees when looking along each of `steps`, stored row by
+/// row. A tree is visible if it's visible from at least one edge.
+struct Survey {
+    width: usize,
+    visible_from: Vec<VisibleFrom>,
+    scores: Vec<u64>,
+}
+
+impl Survey {
+    fn to_rows<T: Clone>(&self, cells: &[T]) -> Vec<Vec<T>> {
+        cells.chunks(self.width).map(<[T]>::to_vec).collect()
+    }
+
+    fn visible_count(&self) -> usize {
+        self.visible_from
+            .iter()
+            .filter(|visible_from| visible_from.is_visible())
+            .count()
+    }
+
+    fn highest_score(&self) -> u64 {
+        self.scores.iter().copied().max().unwrap_or(0)
+    }
+
+    /// whether each tree is visible from outside the forest
+    fn visibility_map(&self) -> Vec<Vec<bool>> {
+        let visible: Vec<bool> = self
+            .visible_from
+            .iter()
+            .map(VisibleFrom::is_visible)
+            .collect();
+        self.to_rows(&visible)
+    }
+
+    /// which edges each tree is visible from
+    fn visible_from_map(&self) -> Vec<Vec<VisibleFrom>> {
+        self.to_rows(&self.visible_from)
+    }
+
+    /// the scenic score of every tree
+    fn score_map(&self) -> Vec<Vec<u64>> {
+        self.to_rows(&self.scores)
+    }
 }
 
-fn look_vertical(grid: &Grid, map: &mut Vec<Vec<bool>>, xs: &[usize], ys: &[usize]) {
-    for x in xs.iter().skip(1) {
-        let mut curr_highest = grid[ys[0]][*x];
-        for y in ys.iter().skip(1) {
-            let height = grid[*y][*x];
-            if height > curr_highest {
-                curr_highest = height;
-                map[*y][*x] = true;
+/// Looks along one line of trees, starting at `start` and moving by `step`
+/// until the edge. `look(x, y, dir, distance, visible)` is called for every
+/// tree, once looking towards the start and once towards the end, with
+/// `dirs` telling which is which. `distance` is how many trees it can see
+/// that way and `visible` whether nothing blocks the view all the way to
+/// the edge. A side that's `None` in `dirs` isn't reported.
+///
+/// `stack` holds the trees that haven't found anything at least as tall
+/// further down the line yet, so their heights strictly decrease and every
+/// tree is pushed and popped once.
+fn look_along_line(
+    grid: &Grid,
+    start: (usize, usize),
+    step: Step,
+    dirs: (Option<usize>, Option<usize>),
+    stack: &mut Vec<(usize, usize, usize)>,
+    look: &mut impl FnMut(usize, usize, usize, usize, bool),
+) {
+    let (towards_start, towards_end) = dirs;
+    let (h, w) = (grid.len() as isize, grid[0].len() as isize);
+    stack.clear();
+
+    let (mut x, mut y) = (start.0 as isize, start.1 as isize);
+    let mut k = 0;
+    while (0..w).contains(&x) && (0..h).contains(&y) {
+        let (ux, uy) = (x as usize, y as usize);
+        let height = grid[uy][ux];
+
+        let mut blocker = None;
+        while let Some(&(bx, by, bk)) = stack.last() {
+            if grid[by][bx] > height {
+                break;
+            }
+            // this tree is the first one at least as tall as the one on the stack
+            if let Some(dir) = towards_end {
+                look(bx, by, dir, k - bk, false);
+            }
+            stack.pop();
+            if grid[by][bx] == height {
+                blocker = Some(bk);
+            }
+        }
+
+        if let Some(dir) = towards_start {
+            match blocker.or(stack.last().map(|&(_, _, bk)| bk)) {
+                Some(bk) => look(ux, uy, dir, k - bk, false),
+                None => look(ux, uy, dir, k, true),
             }
         }
+
+        stack.push((ux, uy, k));
+        x += step.0;
+        y += step.1;
+        k += 1;
+    }
+
+    // nothing further down the line is as tall
+    if let Some(dir) = towards_end {
+        for &(bx, by, bk) in stack.iter() {
+            look(bx, by, dir, k - 1 - bk, true);
+        }
     }
 }
 
-fn look_and_mark(look_from: Direction, grid: &Grid, map: &mut Vec<Vec<bool>>) {
-    let (xs, ys) = get_tree_pos_across_axis(&look_from, grid);
+/// Groups each step with its opposite, if there is one, since they share
+/// the same lines. Gives `(step, opposite)` as indices into `steps`.
+fn step_pairs(steps: &[Step]) -> Vec<(usize, Option<usize>)> {
+    let mut done = vec![false; steps.len()];
+    let mut pairs = vec![];
+
+    for i in 0..steps.len() {
+        if done[i] {
+            continue;
+        }
+        done[i] = true;
 
-    match &look_from {
-        Left | Right => look_horizontal(grid, map, &xs, &ys),
-        Down | Up => look_vertical(grid, map, &xs, &ys),
+        let step = steps[i];
+        assert!(step != (0, 0), "can't look along a step of (0, 0)");
+        let opposite = (i + 1..steps.len()).find(|&j| !done[j] && steps[j] == (-step.0, -step.1));
+        if let Some(j) = opposite {
+            done[j] = true;
+        }
+        pairs.push((i, opposite));
     }
+
+    pairs
 }
 
-fn get_visible_trees(grid: &Grid) -> usize {
-    let mut map = vec![vec![false; grid[0].len()]; grid.len()];
-    map[0].fill(true);
-    let last = map.len() - 1;
-    map[last].fill(true);
+/// Calls `look` for every tree and every step in `steps`, passing the
+/// step's index. See `look_along_line`. A step and its opposite share the
+/// same lines, so they're done in one go.
+fn look_everywhere(
+    grid: &Grid,
+    steps: &[Step],
+    mut look: impl FnMut(usize, usize, usize, usize, bool),
+) {
+    let (h, w) = (grid.len() as isize, grid[0].len() as isize);
+    let mut stack = Vec::with_capacity(h.max(w) as usize);
 
-    for row in map.iter_mut() {
-        row[0] = true;
-        let last = row.len() - 1;
-        row[last] = true;
+    for (i, opposite) in step_pairs(steps) {
+        let step = steps[i];
+
+        // every line starts at a tree with nothing before it
+        for y in 0..h {
+            for x in 0..w {
+                let (px, py) = (x - step.0, y - step.1);
+                if (0..w).contains(&px) && (0..h).contains(&py) {
+                    continue;
+                }
+                look_along_line(
+                    grid,
+                    (x as usize, y as usize),
+                    step,
+                    (opposite, Some(i)),
+                    &mut stack,
+                    &mut look,
+                );
+            }
+        }
     }
+}
+
+/// looks along every step in `steps`, at most 32 of them. Scores saturate
+/// instead of overflowing.
+fn survey(grid: &Grid, steps: &[Step]) -> Survey {
+    assert!(steps.len() <= 32, "at most 32 directions are supported");
 
-    for dir in [Up, Down, Left, Right] {
-        look_and_mark(dir, grid, &mut map);
+    let width = grid[0].len();
+    let mut visible_from = vec![VisibleFrom::default(); width * grid.len()];
+    let mut scores: Vec<u64> = vec![1; width * grid.len()];
+
+    look_everywhere(grid, steps, |x, y, dir, distance, seen_from_edge| {
+        if seen_from_edge {
+            visible_from[y * width + x].add(dir);
+        }
+        scores[y * width + x] = scores[y * width + x].saturating_mul(distance as u64);
+    });
+
+    Survey {
+        width,
+        visible_from,
+        scores,
     }
+}
 
-    map.iter().flatten().filter(|visible| **visible).count()
+fn get_visible_trees(grid: &Grid, steps: &[Step]) -> usize {
+    survey(grid, steps).visible_count()
 }
 
-fn calculate_scenic_score(grid: &Grid, x: usize, y: usize) -> u32 {
-    [Up, Down, Left, Right]
-        .iter()
-        .map(|dir| calculate_score_in_dir(grid, x, y, &dir))
-        .product::<u32>()
+fn get_highest_scenic_score(grid: &Grid, steps: &[Step]) -> u64 {
+    survey(grid, steps).highest_score()
 }
 
-fn calculate_score_in_dir(grid: &Grid, x: usize, y: usize, dir: &Direction) -> u32 {
-    let treehouse_height = grid[y][x];
-    let iter: Vec<&u8> = match &dir {
-        Up => grid.iter().take(y).map(|row| &row[x]).rev().collect(),
-        Down => grid.iter().skip(y + 1).map(|row| &row[x]).collect(),
-        Left => grid[y].iter().take(x).rev().collect(),
-        Right => grid[y].iter().skip(x + 1).collect(),
+/// a random forest for benchmarking, heights are 0 to 9
+fn generate_forest(width: usize, height: usize, seed: u64) -> Grid {
+    // xorshift64
+    let mut state = seed | 1;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
     };
 
-    for (idx, tree) in iter.iter().enumerate() {
-        if **tree >= treehouse_height {
-            return idx as u32 + 1;
-        }
+    (0..height)
+        .map(|_| (0..width).map(|_| (next() % 10) as u8).collect())
+        .collect()
+}
+
+fn bench(size: usize, steps: &[Step]) {
+    let start = Instant::now();
+    let grid = generate_forest(size, size, 2022);
+    println!("generating {0}x{0}: {1:?}", size, start.elapsed());
+
+    let start = Instant::now();
+    let survey = survey(&grid, steps);
+    println!("survey: {:?}", start.elapsed());
+
+    let visible = survey.visible_count();
+    let (best, score) = survey
+        .scores
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, score)| **score)
+        .unwrap();
+    println!("{} visible", visible);
+    println!(
+        "best score {} at ({}, {})",
+        score,
+        best % survey.width,
+        best / survey.width
+    );
+
+    let start = Instant::now();
+    let mut forest = forest::Forest::new(grid, steps);
+    println!("building a forest: {:?}", start.elapsed());
+
+    let changes = generate_forest(3, 1000, 2023);
+    let start = Instant::now();
+    for change in &changes {
+        let (x, y) = (change[0] as usize, change[1] as usize);
+        forest.set(x * size / 10, y * size / 10, change[2]);
     }
-    iter.len() as u32
+    println!("{} updates: {:?}", changes.len(), start.elapsed());
+    println!(
+        "{} visible, best score {}",
+        forest.visible_count(),
+        forest.highest_score()
+    );
+}
+
+fn read_grid(fname: &str) -> Grid {
+    let grid = if fname == "-" {
+        parsing::parse_reader(io::stdin().lock())
+    } else {
+        parsing::parse_input(fname)
+    };
+    grid.unwrap_or_else(|err| {
+        eprintln!("{}: {}", fname, err);
+        process::exit(1);
+    })
 }
 
-fn get_highest_scenic_score(grid: &Grid) -> u32 {
-    let mut hiscore = 0;
-    for y in 1..grid.len() - 1 {
-        for x in 1..grid[0].len() - 1 {
-            let score = calculate_scenic_score(grid, x, y);
-            if score > hiscore {
-                hiscore = score;
+/// `--region x,y,width,height` and `--min-height h` in any order
+fn parse_constraints(opts: &[&str]) -> Option<query::Constraints> {
+    let mut constraints = query::Constraints::default();
+    for pair in opts.chunks(2) {
+        match pair {
+            ["--region", region] => {
+                let numbers: Vec<usize> = region
+                    .split(',')
+                    .map(|n| n.parse().ok())
+                    .collect::<Option<_>>()?;
+                let [x, y, width, height] = numbers[..] else {
+                    return None;
+                };
+                constraints.region = Some(query::Region {
+                    x,
+                    y,
+                    width,
+                    height,
+                });
             }
+            ["--min-height", height] => constraints.min_height = height.parse().ok()?,
+            _ => return None,
         }
     }
+    Some(constraints)
+}
 
-    hiscore
+fn constraints_usage() -> ! {
+    eprintln!("options are --region x,y,width,height and --min-height h");
+    process::exit(1);
 }
 
-fn parse_input(fname: &str) -> Grid {
-    let file = File::open(fname).unwrap();
-    let lines = io::BufReader::new(file).lines();
+fn print_site(site: &query::Site) {
+    println!(
+        "{} at ({}, {}), height {}",
+        site.score, site.x, site.y, site.height
+    );
+}
 
-    lines
-        .filter_map(|line| match line {
-            Ok(line) => Some(
-                line.as_bytes()
-                    .iter()
-                    .map(|chr| chr - ORD_ZERO)
-                    .collect::<Vec<u8>>(),
-            ),
-            Err(_) => None, // Not sure why it would fail
-        })
-        .collect()
+/// writes every per-tree map into `dir`, looking along `steps`
+fn export_maps(grid: &Grid, steps: &[Step], dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let survey = survey(grid, steps);
+    let visible: Vec<Vec<u8>> = survey
+        .visibility_map()
+        .iter()
+        .map(|row| row.iter().map(|&visible| visible as u8).collect())
+        .collect();
+    let visible_from = survey.visible_from_map();
+    let sides: Vec<Vec<u64>> = visible_from
+        .iter()
+        .map(|row| row.iter().map(|sides| sides.count() as u64).collect())
+        .collect();
+    let names: Vec<Vec<String>> = visible_from
+        .iter()
+        .map(|row| row.iter().map(|sides| sides.describe(steps)).collect())
+        .collect();
+    let scores = survey.score_map();
+
+    fs::write(dir.join("visibility.csv"), export::to_csv(&visible))?;
+    fs::write(dir.join("visible_from.csv"), export::to_csv(&names))?;
+    fs::write(dir.join("scores.csv"), export::to_csv(&scores))?;
+    fs::write(dir.join("visibility.pgm"), export::to_pgm(&sides))?;
+    fs::write(dir.join("scores.pgm"), export::to_pgm(&scores))?;
+    fs::write(dir.join("scores.ppm"), export::to_ppm_heatmap(&scores))?;
+    Ok(())
 }
 
 fn main() {
-    let grid = parse_input("input.txt");
-    let ans = get_visible_trees(&grid);
-    println!("{}", ans);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
 
-    let ans = get_highest_scenic_score(&grid);
-    println!("{}", ans);
+    // our variants of the puzzle look along the diagonals too
+    let steps: &[Step] = match args.iter().position(|&arg| arg == "--eight-way") {
+        Some(i) => {
+            args.remove(i);
+            &EIGHT_WAY
+        }
+        None => &FOUR_WAY,
+    };
+
+    match args[..] {
+        ["bench"] => bench(5000, steps),
+        ["bench", size] => bench(size.parse().expect("size should be a number"), steps),
+        ["export", fname, dir] => {
+            let grid = read_grid(fname);
+            export_maps(&grid, steps, Path::new(dir)).expect("failed to write maps");
+        }
+        ["top", fname, k, ref opts @ ..] => {
+            let k = k.parse().expect("k should be a number");
+            let constraints = parse_constraints(opts).unwrap_or_else(|| constraints_usage());
+            let grid = read_grid(fname);
+            let survey = survey(&grid, steps);
+            for site in query::top_k(&grid, &survey, k, &constraints) {
+                print_site(&site);
+            }
+        }
+        ["best", fname, ref opts @ ..] => {
+            let constraints = parse_constraints(opts).unwrap_or_else(|| constraints_usage());
+            let grid = read_grid(fname);
+            let survey = survey(&grid, steps);
+            match query::best(&grid, &survey, &constraints) {
+                Some(site) => print_site(&site),
+                None => println!("no tree fits"),
+            }
+        }
+        ["view", fname, x, y] => {
+            let viewpoint = (
+                x.parse().expect("x should be a number"),
+                y.parse().expect("y should be a number"),
+            );
+            let grid = read_grid(fname);
+            let (h, w) = (grid.len() as isize, grid[0].len() as isize);
+            if (0..w).contains(&viewpoint.0) && (0..h).contains(&viewpoint.1) {
+                eprintln!("({}, {}) is inside the forest", x, y);
+                process::exit(1);
+            }
+            println!("{}", query::visible_from_viewpoint(&grid, viewpoint, steps));
+        }
+        ["viewshed", fname, x, y, z, ref out @ ..] if out.len() <= 1 => {
+            let number = |n: &str| n.parse().expect("coordinates should be numbers");
+            let observer = viewshed::Observer {
+                x: number(x),
+                y: number(y),
+                z: number(z),
+            };
+            let grid = read_grid(fname);
+            println!("{}", viewshed::count_visible(&grid, observer));
+
+            if let [out] = out {
+                let seen: Vec<Vec<u64>> = viewshed::viewshed(&grid, observer)
+                    .iter()
+                    .map(|row| row.iter().map(|&seen| seen as u64).collect())
+                    .collect();
+                fs::write(out, export::to_pgm(&seen)).expect("failed to write the image");
+            }
+        }
+        ["update", fname, ref changes @ ..] if !changes.is_empty() && changes.len() % 3 == 0 => {
+            let grid = read_grid(fname);
+            let (h, w) = (grid.len(), grid[0].len());
+            let mut forest = forest::Forest::new(grid, steps);
+
+            for change in changes.chunks(3) {
+                let number = |n: &str| n.parse().expect("changes should be numbers");
+                let (x, y, height) = (number(change[0]), number(change[1]), number(change[2]));
+                if x >= w || y >= h || height > u8::MAX as usize {
+                    eprintln!("can't put a tree of height {} at ({}, {})", height, x, y);
+                    process::exit(1);
+                }
+                forest.set(x, y, height as u8);
+                println!(
+                    "({}, {}) height {} score {} seen from {}: {} visible, best score {}",
+                    x,
+                    y,
+                    forest.height(x, y),
+                    forest.score(x, y),
+                    forest.visible_from(x, y).describe(steps),
+                    forest.visible_count(),
+                    forest.highest_score()
+                );
+            }
+        }
+        [] | [_] => {
+            let grid = read_grid(args.first().copied().unwrap_or("input.txt"));
+            let ans = get_visible_trees(&grid, steps);
+            println!("{}", ans);
+
+            let ans = get_highest_scenic_score(&grid, steps);
+            println!("{}", ans);
+        }
+        _ => {
+            eprintln!("usage: day8 [--eight-way] [input file, - for stdin]");
+            eprintln!("       day8 [--eight-way] bench [size]");
+            eprintln!("       day8 [--eight-way] export <input file> <output dir>");
+            eprintln!(
+                "       day8 [--eight-way] top <input file> <k> [--region x,y,w,h] [--min-height h]"
+            );
+            eprintln!(
+                "       day8 [--eight-way] best <input file> [--region x,y,w,h] [--min-height h]"
+            );
+            eprintln!("       day8 [--eight-way] view <input file> <x> <y>");
+            eprintln!("       day8 [--eight-way] update <input file> <x> <y> <height> [...]");
+            eprintln!("       day8 viewshed <input file> <x> <y> <elevation> [output.pgm]");
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{get_highest_scenic_score, get_visible_trees};
+    use crate::{
+        generate_forest, get_highest_scenic_score, get_visible_trees, step_name, survey, Grid,
+        Step, EIGHT_WAY, FOUR_WAY,
+    };
+
+    /// the obvious way, looking from every tree along every step
+    fn brute_force(grid: &Grid, steps: &[Step]) -> (usize, u64) {
+        let (h, w) = (grid.len() as isize, grid[0].len() as isize);
+        let mut visible = 0;
+        let mut best = 0;
+
+        for y in 0..h {
+            for x in 0..w {
+                let height = grid[y as usize][x as usize];
+                let mut seen_from_edge = false;
+                let mut score = 1;
+                for &(dx, dy) in steps {
+                    let (mut cx, mut cy) = (x + dx, y + dy);
+                    let mut distance = 0;
+                    let mut blocked = false;
+                    while (0..w).contains(&cx) && (0..h).contains(&cy) {
+                        distance += 1;
+                        if grid[cy as usize][cx as usize] >= height {
+                            blocked = true;
+                            break;
+                        }
+                        cx += dx;
+                        cy += dy;
+                    }
+                    seen_from_edge |= !blocked;
+                    score *= distance;
+                }
+                visible += seen_from_edge as usize;
+                best = best.max(score);
+            }
+        }
+
+        (visible, best)
+    }
+
+    #[test]
+    fn test_maps() {
+        let grid = vec![
+            vec![3, 0, 3, 7, 3],
+            vec![2, 5, 5, 1, 2],
+            vec![6, 5, 3, 3, 2],
+            vec![3, 3, 5, 4, 9],
+            vec![3, 5, 3, 9, 0],
+        ];
+
+        let survey = survey(&grid, &FOUR_WAY);
+        let visible = survey.visibility_map();
+        assert_eq!(visible[1], [true, true, true, false, true]);
+        assert_eq!(visible[2], [true, true, false, true, true]);
+
+        let visible_from: Vec<String> = survey.visible_from_map()[1]
+            .iter()
+            .map(|sides| sides.describe(&FOUR_WAY))
+            .collect();
+        assert_eq!(visible_from, ["L", "UL", "UR", "-", "R"]);
+
+        let scores = survey.score_map();
+        assert_eq!(scores[1], [0, 1, 4, 1, 0]);
+        assert_eq!(scores[3], [0, 1, 8, 3, 0]);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        for seed in 0..30 {
+            let grid = generate_forest(1 + seed as usize % 7, 1 + seed as usize % 11, seed);
+            assert_eq!(
+                (
+                    get_visible_trees(&grid, &FOUR_WAY),
+                    get_highest_scenic_score(&grid, &FOUR_WAY)
+                ),
+                brute_force(&grid, &FOUR_WAY)
+            );
+        }
+    }
+
+    #[test]
+    fn test_other_steps_match_brute_force() {
+        // knight's moves, some without their opposite, and a repeated step
+        let odd_steps = [(2, 1), (-2, -1), (1, -2), (-1, 3), (0, 2), (0, 2)];
+
+        for seed in 0..30 {
+            let grid = generate_forest(1 + seed as usize % 7, 1 + seed as usize % 11, seed);
+            for steps in [&EIGHT_WAY[..], &odd_steps[..]] {
+                let survey = survey(&grid, steps);
+                assert_eq!(
+                    (survey.visible_count(), survey.highest_score()),
+                    brute_force(&grid, steps)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_eight_way() {
+        let grid = vec![
+            vec![3, 0, 3, 7, 3],
+            vec![2, 5, 5, 1, 2],
+            vec![6, 5, 3, 3, 2],
+            vec![3, 3, 5, 4, 9],
+            vec![3, 5, 3, 9, 0],
+        ];
+        let survey = survey(&grid, &EIGHT_WAY);
+
+        // taller trees sit on every diagonal of the middle 3 as well
+        assert_eq!(survey.visible_from_map()[2][2].describe(&EIGHT_WAY), "-");
+        assert_eq!(survey.visible_from_map()[1][3].describe(&EIGHT_WAY), "-");
+        assert_eq!(
+            survey.visible_from_map()[1][1].describe(&EIGHT_WAY),
+            "U+L+UL+UR+DR"
+        );
+        assert_eq!(survey.score_map()[3][2], 8 * 2);
+
+        assert_eq!(step_name((1, 1)), "DR");
+        assert_eq!(step_name((2, -1)), "(2 -1)");
+    }
 
     #[test]
     fn test_get_visible_trees() {
@@ -159,7 +718,7 @@ mod tests {
             vec![3, 5, 3, 9, 0],
         ];
 
-        assert_eq!(get_visible_trees(&grid), 21);
+        assert_eq!(get_visible_trees(&grid, &FOUR_WAY), 21);
     }
 
     #[test]
@@ -172,6 +731,6 @@ mod tests {
             vec![3, 5, 3, 9, 0],
         ];
 
-        assert_eq!(get_highest_scenic_score(&grid), 8);
+        assert_eq!(get_highest_scenic_score(&grid, &FOUR_WAY), 8);
     }
 }
@@ -0,0 +1,248 @@
+//! Reading a forest from a file.
+//!
+//! Two formats are accepted. The puzzle's, one digit per tree:
+//!
+//! ```text
+//! 30373
+//! 25512
+//! ```
+//!
+//! and one where heights can have several digits and are separated by
+//! spaces or tabs, which is picked whenever the first row has any between
+//! its heights. Trailing spaces and tabs are ignored in both.
+//!
+//! ```text
+//! 3 0 3 17
+//! 2 5 12 1
+//! ```
+
+use crate::Grid;
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+};
+
+const ORD_ZERO: u8 = 48;
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    /// `line` and `column` are 1-indexed
+    InvalidHeight {
+        line: usize,
+        column: usize,
+        found: String,
+    },
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    Empty,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "error reading input: {}", err),
+            ParseError::InvalidHeight {
+                line,
+                column,
+                found,
+            } => write!(f, "{}:{}: {:?} is not a tree height", line, column, found),
+            ParseError::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected {} trees like the rows before, found {}",
+                line, expected, found
+            ),
+            ParseError::Empty => write!(f, "there are no trees"),
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
+pub fn parse_input(fname: &str) -> Result<Grid, ParseError> {
+    let file = File::open(fname)?;
+    parse_reader(file)
+}
+
+pub fn parse_reader(reader: impl Read) -> Result<Grid, ParseError> {
+    let mut rows = vec![];
+    let mut spaced = None;
+    let mut blank_lines = 0;
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        let line_no = i + 1;
+
+        if line.trim().is_empty() {
+            // only allowed at the end, checked when the next row shows up
+            blank_lines += 1;
+            continue;
+        }
+
+        let spaced =
+            *spaced.get_or_insert_with(|| line.trim_matches(is_separator).contains(is_separator));
+        let row = if spaced {
+            parse_spaced_row(line, line_no)?
+        } else {
+            parse_dense_row(line, line_no)?
+        };
+
+        if let Some(first) = rows.first().map(Vec::len) {
+            if blank_lines > 0 {
+                return Err(ParseError::RaggedRow {
+                    line: line_no - blank_lines,
+                    expected: first,
+                    found: 0,
+                });
+            }
+            if row.len() != first {
+                return Err(ParseError::RaggedRow {
+                    line: line_no,
+                    expected: first,
+                    found: row.len(),
+                });
+            }
+        }
+        blank_lines = 0;
+        rows.push(row);
+    }
+
+    if rows.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    Ok(rows)
+}
+
+/// one digit per tree
+fn parse_dense_row(line: &str, line_no: usize) -> Result<Vec<u8>, ParseError> {
+    line.trim_end_matches(is_separator)
+        .chars()
+        .enumerate()
+        .map(|(i, chr)| {
+            if chr.is_ascii_digit() {
+                Ok(chr as u8 - ORD_ZERO)
+            } else {
+                Err(ParseError::InvalidHeight {
+                    line: line_no,
+                    column: i + 1,
+                    found: chr.to_string(),
+                })
+            }
+        })
+        .collect()
+}
+
+/// spaces and tabs, a stray `\r` is still an error
+fn is_separator(chr: char) -> bool {
+    chr == ' ' || chr == '\t'
+}
+
+/// numbers separated by spaces or tabs
+fn parse_spaced_row(line: &str, line_no: usize) -> Result<Vec<u8>, ParseError> {
+    let mut row = vec![];
+    let mut rest = line;
+
+    loop {
+        rest = rest.trim_start_matches(is_separator);
+        if rest.is_empty() {
+            return Ok(row);
+        }
+
+        let token_len = rest.find(is_separator).unwrap_or(rest.len());
+        let token = &rest[..token_len];
+        let column = line[..line.len() - rest.len()].chars().count() + 1;
+
+        let is_number = token.bytes().all(|b| b.is_ascii_digit());
+        match token.parse() {
+            Ok(height) if is_number => row.push(height),
+            _ => {
+                return Err(ParseError::InvalidHeight {
+                    line: line_no,
+                    column,
+                    found: token.to_string(),
+                })
+            }
+        }
+
+        rest = &rest[token_len..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_reader, ParseError};
+    use crate::Grid;
+
+    fn parse_str(input: &str) -> Result<Grid, ParseError> {
+        parse_reader(input.as_bytes())
+    }
+
+    #[test]
+    fn test_parse_dense() {
+        assert_eq!(
+            parse_str("303\n255\n").unwrap(),
+            vec![vec![3, 0, 3], vec![2, 5, 5]]
+        );
+        assert_eq!(
+            parse_str("303\r\n255\r\n\r\n").unwrap(),
+            vec![vec![3, 0, 3], vec![2, 5, 5]]
+        );
+        // trailing whitespace doesn't make it spaced
+        assert_eq!(
+            parse_str("303 \n255\t\n").unwrap(),
+            vec![vec![3, 0, 3], vec![2, 5, 5]]
+        );
+    }
+
+    #[test]
+    fn test_parse_spaced() {
+        assert_eq!(
+            parse_str("3  0 17\n\t2 5 255 \r\n").unwrap(),
+            vec![vec![3, 0, 17], vec![2, 5, 255]]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let tests = [
+            ("303\n2a5\n", "2:2: \"a\" is not a tree height"),
+            (
+                "303\n25\n",
+                "2: expected 3 trees like the rows before, found 2",
+            ),
+            (
+                "303\n\n255\n",
+                "2: expected 3 trees like the rows before, found 0",
+            ),
+            ("1 2\n3 256\n", "2:3: \"256\" is not a tree height"),
+            ("1 2\n3 -4\n", "2:3: \"-4\" is not a tree height"),
+            (
+                "1 2\n34\n",
+                "2: expected 2 trees like the rows before, found 1",
+            ),
+            ("\n\n", "there are no trees"),
+        ];
+
+        for test in tests {
+            assert_eq!(parse_str(test.0).unwrap_err().to_string(), test.1);
+        }
+
+        assert!(matches!(
+            parse_str("30\r3\n"),
+            Err(ParseError::InvalidHeight { column: 3, .. })
+        ));
+    }
+}
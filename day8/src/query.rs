@@ -0,0 +1,228 @@
+//! Questions about a surveyed forest beyond the puzzle's two answers:
+//! several treehouse sites at once, sites meeting some constraints, and
+//! what someone standing outside the forest gets to see.
+
+use crate::{Grid, Step, Survey};
+use std::collections::HashSet;
+
+/// a candidate treehouse location
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Site {
+    pub x: usize,
+    pub y: usize,
+    pub height: u8,
+    pub score: u64,
+}
+
+/// `width` by `height` trees with `(x, y)` as the top left corner
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// which trees are allowed to hold a treehouse
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Constraints {
+    pub region: Option<Region>,
+    pub min_height: u8,
+}
+
+impl Constraints {
+    fn allows(&self, x: usize, y: usize, height: u8) -> bool {
+        let inside = match self.region {
+            Some(region) => region.contains(x, y),
+            None => true,
+        };
+        height >= self.min_height && inside
+    }
+}
+
+/// The `k` allowed sites with the highest scenic scores, best first. Equal
+/// scores are ordered top to bottom, then left to right.
+pub fn top_k(grid: &Grid, survey: &Survey, k: usize, constraints: &Constraints) -> Vec<Site> {
+    let mut sites: Vec<Site> = survey
+        .scores
+        .iter()
+        .enumerate()
+        .map(|(i, &score)| {
+            let (x, y) = (i % survey.width, i / survey.width);
+            Site {
+                x,
+                y,
+                height: grid[y][x],
+                score,
+            }
+        })
+        .filter(|site| constraints.allows(site.x, site.y, site.height))
+        .collect();
+
+    let order = |a: &Site, b: &Site| b.score.cmp(&a.score).then((a.y, a.x).cmp(&(b.y, b.x)));
+    if k < sites.len() {
+        // no need to sort the whole forest
+        sites.select_nth_unstable_by(k, order);
+        sites.truncate(k);
+    }
+    sites.sort_unstable_by(order);
+    sites
+}
+
+/// the best allowed site, `None` if no tree is allowed
+pub fn best(grid: &Grid, survey: &Survey, constraints: &Constraints) -> Option<Site> {
+    top_k(grid, survey, 1, constraints).pop()
+}
+
+/// The steps `k` for which `v + k * d` lies in `0..n`, `None` if there are
+/// none. They're always a single range since the forest is a rectangle.
+fn steps_inside(v: isize, d: isize, n: isize) -> Option<(isize, isize)> {
+    if d == 0 {
+        return (0..n).contains(&v).then_some((isize::MIN, isize::MAX));
+    }
+    // looking the other way is looking from the mirrored position
+    let (v, d) = if d < 0 { (n - 1 - v, -d) } else { (v, d) };
+    let first = -(v.div_euclid(d));
+    let last = (n - 1 - v).div_euclid(d);
+    (first <= last).then_some((first, last))
+}
+
+/// How many trees can be seen from `viewpoint`, somewhere outside the
+/// forest, looking along each of `steps`. As with the edges, a tree is
+/// seen when every tree before it on the way in is shorter. Trees seen
+/// along several steps are counted once.
+pub fn visible_from_viewpoint(grid: &Grid, viewpoint: (isize, isize), steps: &[Step]) -> usize {
+    let (h, w) = (grid.len() as isize, grid[0].len() as isize);
+    let (vx, vy) = viewpoint;
+    assert!(
+        !((0..w).contains(&vx) && (0..h).contains(&vy)),
+        "the viewpoint has to be outside the forest"
+    );
+
+    let mut seen = HashSet::new();
+    for &(dx, dy) in steps {
+        assert!((dx, dy) != (0, 0), "can't look along a step of (0, 0)");
+        let (Some(along_x), Some(along_y)) = (steps_inside(vx, dx, w), steps_inside(vy, dy, h))
+        else {
+            continue;
+        };
+        // behind the viewpoint doesn't count
+        let first = along_x.0.max(along_y.0).max(1);
+        let last = along_x.1.min(along_y.1);
+
+        let mut tallest = None;
+        for k in first..=last {
+            let (x, y) = (vx + k * dx, vy + k * dy);
+            let height = grid[y as usize][x as usize];
+            let taller = match tallest {
+                Some(tallest) => height > tallest,
+                None => true,
+            };
+            if taller {
+                seen.insert((x, y));
+                tallest = Some(height);
+            }
+        }
+    }
+
+    seen.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best, top_k, visible_from_viewpoint, Constraints, Region, Site};
+    use crate::{survey, EIGHT_WAY, FOUR_WAY};
+
+    fn example() -> Vec<Vec<u8>> {
+        vec![
+            vec![3, 0, 3, 7, 3],
+            vec![2, 5, 5, 1, 2],
+            vec![6, 5, 3, 3, 2],
+            vec![3, 3, 5, 4, 9],
+            vec![3, 5, 3, 9, 0],
+        ]
+    }
+
+    #[test]
+    fn test_top_k() {
+        let grid = example();
+        let survey = survey(&grid, &FOUR_WAY);
+        let site = |x, y, height, score| Site {
+            x,
+            y,
+            height,
+            score,
+        };
+
+        assert_eq!(
+            top_k(&grid, &survey, 4, &Constraints::default()),
+            [
+                site(2, 3, 5, 8),
+                site(1, 2, 5, 6),
+                site(2, 1, 5, 4),
+                site(3, 3, 4, 3)
+            ]
+        );
+        assert_eq!(
+            top_k(&grid, &survey, 100, &Constraints::default()).len(),
+            25
+        );
+        assert!(top_k(&grid, &survey, 0, &Constraints::default()).is_empty());
+    }
+
+    #[test]
+    fn test_best_with_constraints() {
+        let grid = example();
+        let survey = survey(&grid, &FOUR_WAY);
+
+        let top_rows = Constraints {
+            region: Some(Region {
+                x: 0,
+                y: 0,
+                width: 5,
+                height: 2,
+            }),
+            min_height: 0,
+        };
+        let best_site = best(&grid, &survey, &top_rows).unwrap();
+        assert_eq!((best_site.x, best_site.y, best_site.score), (2, 1, 4));
+
+        let tall = Constraints {
+            region: None,
+            min_height: 6,
+        };
+        let best_site = best(&grid, &survey, &tall).unwrap();
+        assert_eq!((best_site.x, best_site.y, best_site.height), (3, 0, 7));
+
+        let impossible = Constraints {
+            region: None,
+            min_height: 10,
+        };
+        assert_eq!(best(&grid, &survey, &impossible), None);
+    }
+
+    #[test]
+    fn test_visible_from_viewpoint() {
+        let grid = example();
+
+        // left of the second row, looking right: 2 and the first 5
+        assert_eq!(visible_from_viewpoint(&grid, (-1, 1), &[(1, 0)]), 2);
+        // further away changes nothing
+        assert_eq!(visible_from_viewpoint(&grid, (-10, 1), &[(1, 0)]), 2);
+        // looking away from the forest
+        assert_eq!(visible_from_viewpoint(&grid, (-1, 1), &[(-1, 0)]), 0);
+        // up the last column then along the diagonal into the corner
+        assert_eq!(visible_from_viewpoint(&grid, (4, 5), &[(0, -1)]), 2);
+        assert_eq!(visible_from_viewpoint(&grid, (5, 5), &[(-1, -1)]), 3);
+        // only the diagonal gets into the forest from past the corner
+        assert_eq!(visible_from_viewpoint(&grid, (-1, -1), &EIGHT_WAY), 2);
+        // the 7 is seen both ways but counted once
+        assert_eq!(visible_from_viewpoint(&grid, (-1, 0), &[(1, 0), (2, 0)]), 3);
+    }
+}
@@ -0,0 +1,231 @@
+//! Which trees someone standing anywhere, at any elevation, can actually
+//! see, following real lines of sight instead of looking along grid lines.
+//!
+//! Every cell of a height field is a flat topped column one unit wide,
+//! centred on its integer coordinates. A tree is seen when the straight line
+//! from the observer's eye to the middle of its top passes strictly above
+//! every column in between, touching a top blocks the view like an equally
+//! tall tree does in the puzzle. The cell the observer stands in never
+//! blocks anything.
+
+use crate::Grid;
+
+/// anything that has a height at every cell of a `width` by `height` area
+pub trait HeightField {
+    fn size(&self) -> (usize, usize);
+    fn height_at(&self, x: usize, y: usize) -> f64;
+}
+
+impl HeightField for Grid {
+    fn size(&self) -> (usize, usize) {
+        (self.first().map_or(0, Vec::len), self.len())
+    }
+
+    fn height_at(&self, x: usize, y: usize) -> f64 {
+        self[y][x] as f64
+    }
+}
+
+/// where the eye is, `z` being the elevation on the same scale as heights
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observer {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// the column containing `(x, y)`, which may be outside the field
+fn cell_of(x: f64, y: f64) -> (i64, i64) {
+    ((x + 0.5).floor() as i64, (y + 0.5).floor() as i64)
+}
+
+/// Whether the top of the column at `target` can be seen by `observer`.
+/// Marches along the line one column at a time, each column the line
+/// passes over is checked where the line enters and leaves it, since the
+/// line is lowest at one of those.
+pub fn can_see(field: &impl HeightField, observer: Observer, target: (usize, usize)) -> bool {
+    let (w, h) = field.size();
+    let (tx, ty) = (target.0 as f64, target.1 as f64);
+    let tz = field.height_at(target.0, target.1);
+    let (dx, dy, dz) = (tx - observer.x, ty - observer.y, tz - observer.z);
+
+    let start = cell_of(observer.x, observer.y);
+    let end = (target.0 as i64, target.1 as i64);
+    let mut cell = start;
+
+    // how far along the line the next column boundary is on each axis, and
+    // how far apart those boundaries are
+    let axis = |from: f64, cell: i64, d: f64| -> (i64, f64, f64) {
+        if d > 0.0 {
+            (1, (cell as f64 + 0.5 - from) / d, 1.0 / d)
+        } else if d < 0.0 {
+            (-1, (cell as f64 - 0.5 - from) / d, -1.0 / d)
+        } else {
+            (0, f64::INFINITY, f64::INFINITY)
+        }
+    };
+    let (step_x, mut next_x, delta_x) = axis(observer.x, cell.0, dx);
+    let (step_y, mut next_y, delta_y) = axis(observer.y, cell.1, dy);
+
+    // the line ends at the top of the target, 1 along it. Rounding can
+    // carry it past the target's column without entering it, then every
+    // column it crossed on the way has already been checked.
+    let mut enter = 0.0;
+    while enter < 1.0 {
+        if cell == end {
+            return true;
+        }
+
+        let leave = next_x.min(next_y).min(1.0);
+        let inside = (0..w as i64).contains(&cell.0) && (0..h as i64).contains(&cell.1);
+        if cell != start && inside {
+            let column = field.height_at(cell.0 as usize, cell.1 as usize);
+            let lowest = (observer.z + dz * enter).min(observer.z + dz * leave);
+            if lowest <= column {
+                return false;
+            }
+        }
+
+        // through a corner the line only touches the columns beside it
+        let corner = (next_x - next_y).abs() < 1e-9;
+        let (along_x, along_y) = (next_x < next_y || corner, next_y < next_x || corner);
+        if along_x {
+            cell.0 += step_x;
+            enter = next_x;
+            next_x += delta_x;
+        }
+        if along_y {
+            cell.1 += step_y;
+            enter = next_y;
+            next_y += delta_y;
+        }
+        if !along_x && !along_y {
+            break;
+        }
+    }
+
+    // nothing in the way before the line got to the target
+    true
+}
+
+/// whether each cell is visible from `observer`, row by row
+pub fn viewshed(field: &impl HeightField, observer: Observer) -> Vec<Vec<bool>> {
+    let (w, h) = field.size();
+    (0..h)
+        .map(|y| (0..w).map(|x| can_see(field, observer, (x, y))).collect())
+        .collect()
+}
+
+pub fn count_visible(field: &impl HeightField, observer: Observer) -> usize {
+    viewshed(field, observer)
+        .iter()
+        .flatten()
+        .filter(|&&seen| seen)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{can_see, count_visible, viewshed, Observer};
+    use crate::generate_forest;
+
+    fn eye(x: f64, y: f64, z: f64) -> Observer {
+        Observer { x, y, z }
+    }
+
+    #[test]
+    fn test_flat_ground() {
+        let grid = vec![vec![0; 6]; 4];
+
+        // just above flat ground everything is in view
+        assert_eq!(count_visible(&grid, eye(2.0, 1.0, 0.5)), 24);
+        assert_eq!(count_visible(&grid, eye(-3.0, 10.0, 0.5)), 24);
+        // with the eye at ground level every top is grazed
+        assert!(!can_see(&grid, eye(0.0, 0.0, 0.0), (3, 0)));
+        assert!(can_see(&grid, eye(0.0, 0.0, 0.0), (1, 0)));
+    }
+
+    #[test]
+    fn test_wall() {
+        let grid = vec![vec![1, 1, 5, 1, 1], vec![1, 1, 5, 1, 1]];
+
+        // standing on the left, the wall hides the right
+        let low = viewshed(&grid, eye(0.0, 0.0, 2.0));
+        assert_eq!(low[0], [true, true, true, false, false]);
+        assert_eq!(low[1], [true, true, true, false, false]);
+
+        // high enough to look over it, but not straight down behind it
+        let high = viewshed(&grid, eye(0.0, 0.0, 20.0));
+        assert_eq!(high[0], [true, true, true, false, true]);
+
+        // from above the wall itself
+        assert_eq!(count_visible(&grid, eye(2.0, 0.0, 6.0)), 10);
+    }
+
+    #[test]
+    fn test_never_past_the_target() {
+        let grid = vec![vec![0, 9, 0, 0, 0, 0]];
+
+        // eyes on column edges, where rounding decides which side they're on
+        for x in [-0.5, 0.5, 1.5, 2.5] {
+            let observer = eye(x, 0.0, 1.0);
+            // standing in the wall's column it's no obstacle
+            let own = (x + 0.5).floor() as usize;
+            for target in 0..6 {
+                let behind_wall = own != 1 && target != 1 && (own < 1) != (target < 1);
+                assert_eq!(
+                    can_see(&grid, observer, (target, 0)),
+                    !behind_wall,
+                    "from {} to {}",
+                    x,
+                    target
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let grid = vec![vec![0, 0, 0], vec![0, 3, 0], vec![0, 0, 0]];
+
+        assert!(!can_see(&grid, eye(0.0, 0.0, 1.0), (2, 2)));
+        assert!(can_see(&grid, eye(0.0, 0.0, 1.0), (2, 0)));
+        // the middle is a whole unit wide, a knight's move off the
+        // diagonal still crosses it
+        assert!(!can_see(&grid, eye(0.0, 0.0, 1.0), (2, 1)));
+        assert!(!can_see(&grid, eye(0.0, 0.0, 1.0), (1, 2)));
+        assert!(!can_see(&grid, eye(0.0, 0.0, 7.0), (2, 2)));
+        assert!(can_see(&grid, eye(0.0, 0.0, 13.0), (2, 2)));
+    }
+
+    #[test]
+    fn test_higher_sees_more() {
+        for seed in 0..10 {
+            let grid = generate_forest(12, 9, seed);
+            let observer = eye(seed as f64 - 4.0, 4.5, 0.0);
+
+            let mut seen = viewshed(&grid, observer);
+            for z in 1..15 {
+                let higher = viewshed(
+                    &grid,
+                    Observer {
+                        z: z as f64,
+                        ..observer
+                    },
+                );
+                for (row, higher_row) in seen.iter().zip(&higher) {
+                    for (&was_seen, &now_seen) in row.iter().zip(higher_row) {
+                        assert!(!was_seen || now_seen);
+                    }
+                }
+                seen = higher;
+            }
+            // nothing is in the way from high enough
+            let very_high = Observer { z: 1e6, ..observer };
+            assert!(viewshed(&grid, very_high)
+                .iter()
+                .flatten()
+                .all(|&seen| seen));
+        }
+    }
+}
@@ -1,67 +1,130 @@
 use std::{
     collections::HashSet,
-    fs::File,
+    env,
+    fmt::Display,
+    fs::{self, File},
     io::{BufRead, BufReader},
+    path::Path,
+    process,
+    time::Instant,
 };
 
+mod multi;
+mod nd;
+mod render;
+mod rope;
+mod stats;
+mod trail;
+
+use rope::{Elastic, FollowRule, Orthogonal, Rope, Taut};
+use trail::{Bounds, Trail};
+
 type Coord = (i32, i32);
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 enum Direction {
     Up,
     Right,
     Down,
     Left,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
 }
 
-use Direction::{Down, Left, Right, Up};
-
-// fn do_motion(head: &mut Coord, tail: &mut Coord, line: &str) ->
+use Direction::{Down, DownLeft, DownRight, Left, Right, Up, UpLeft, UpRight};
+
+impl Direction {
+    /// how one step this way changes the coordinates
+    fn vector(&self) -> Coord {
+        match self {
+            Up => (0, 1),
+            Right => (1, 0),
+            Down => (0, -1),
+            Left => (-1, 0),
+            UpRight => (1, 1),
+            UpLeft => (-1, 1),
+            DownRight => (1, -1),
+            DownLeft => (-1, -1),
+        }
+    }
 
-fn parse_line_and_execute(head: Coord, tail: Coord, line: &str) -> (Coord, Coord, HashSet<Coord>) {
-    let (dir, n) = parse_motion(line);
-    let mut trail = HashSet::new();
-    trail.insert(tail);
+    /// where one step this way from `from` leads
+    fn step(&self, from: Coord) -> Coord {
+        let (dx, dy) = self.vector();
+        (from.0 + dx, from.1 + dy)
+    }
 
-    let mut head = head;
-    let mut tail = tail;
+    /// how the direction is written in the input
+    fn token(&self) -> &'static str {
+        match self {
+            Up => "U",
+            Right => "R",
+            Down => "D",
+            Left => "L",
+            UpRight => "UR",
+            UpLeft => "UL",
+            DownRight => "DR",
+            DownLeft => "DL",
+        }
+    }
 
-    for _ in 0..n {
-        (head, tail) = move_once(head, tail, &dir);
-        trail.insert(tail);
+    /// `U`, `R`, `D` and `L`, and for the diagonals an up or down letter
+    /// with a left or right one in either order, `UR` or `RU`
+    fn from_token(token: &str) -> Option<Direction> {
+        let dir = match token {
+            "U" => Up,
+            "R" => Right,
+            "D" => Down,
+            "L" => Left,
+            "UR" | "RU" => UpRight,
+            "UL" | "LU" => UpLeft,
+            "DR" | "RD" => DownRight,
+            "DL" | "LD" => DownLeft,
+            _ => return None,
+        };
+        Some(dir)
     }
+}
 
-    (head, tail, trail)
+#[derive(PartialEq, Debug)]
+enum ParseError {
+    /// not a direction and a count separated by whitespace
+    Malformed(String),
+    UnknownDirection(String),
+    InvalidCount(String),
+    /// more steps than a coordinate can take in one go
+    TooManySteps(String),
 }
 
-fn move_once(head: Coord, tail: Coord, dir: &Direction) -> (Coord, Coord) {
-    let new_head = match &dir {
-        Up => (head.0, head.1 + 1),
-        Right => (head.0 + 1, head.1),
-        Down => (head.0, head.1 - 1),
-        Left => (head.0 - 1, head.1),
-    };
-    let new_tail = tail_follow_new_head(new_head, tail);
-    (new_head, new_tail)
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Malformed(line) => write!(f, "{:?} is not a motion", line),
+            ParseError::UnknownDirection(dir) => write!(f, "{:?} is not a direction", dir),
+            ParseError::InvalidCount(n) => write!(f, "{:?} is not a number of steps", n),
+            ParseError::TooManySteps(n) => {
+                write!(f, "{} steps is over the most there can be, {}", n, i32::MAX)
+            }
+        }
+    }
 }
 
-fn are_adjacent(head: Coord, tail: Coord) -> bool {
-    head.0.abs_diff(tail.0) <= 1 && head.1.abs_diff(tail.1) <= 1
+/// touching along every axis, diagonals included, in any number of
+/// dimensions
+fn are_adjacent<const N: usize>(head: [i32; N], tail: [i32; N]) -> bool {
+    head.iter().zip(tail).all(|(h, t)| h.abs_diff(t) <= 1)
 }
 
-/// gives the new position of the tail given the new position of the head
-fn tail_follow_new_head(new_head: Coord, tail: Coord) -> Coord {
+/// gives the new position of the tail given the new position of the head,
+/// one step closer along every axis they differ on
+fn tail_follow_new_head<const N: usize>(new_head: [i32; N], tail: [i32; N]) -> [i32; N] {
     if are_adjacent(new_head, tail) {
         return tail;
     }
 
-    let x_axis = new_head.0 - tail.0;
-    let y_axis = new_head.1 - tail.1;
-
-    let x_step = constrain_to_one(x_axis);
-    let y_step = constrain_to_one(y_axis);
-
-    (tail.0 + x_step, tail.1 + y_step)
+    std::array::from_fn(|axis| tail[axis] + constrain_to_one(new_head[axis] - tail[axis]))
 }
 
 fn constrain_to_one(n: i32) -> i32 {
@@ -72,103 +135,451 @@ fn constrain_to_one(n: i32) -> i32 {
     }
 }
 
-fn parse_motion(line: &str) -> (Direction, u32) {
-    let dir = match &line[0..1] {
-        "U" => Up,
-        "R" => Right,
-        "D" => Down,
-        "L" => Left,
-        _ => panic!("Error parsing direction"),
+fn parse_motion(line: &str) -> Result<(Direction, u32), ParseError> {
+    parse_motion_with(line, Direction::from_token)
+}
+
+/// a motion whose direction is whatever `direction` makes of its token
+fn parse_motion_with<D>(
+    line: &str,
+    direction: impl Fn(&str) -> Option<D>,
+) -> Result<(D, u32), ParseError> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let [dir, n] = tokens[..] else {
+        return Err(ParseError::Malformed(line.to_string()));
     };
-    let n = line[2..].parse().expect("Error parsing into u32");
-    (dir, n)
+
+    let dir = direction(dir).ok_or(ParseError::UnknownDirection(dir.to_string()))?;
+    let count: u32 = n
+        .parse()
+        .map_err(|_| ParseError::InvalidCount(n.to_string()))?;
+    if count > i32::MAX as u32 {
+        return Err(ParseError::TooManySteps(n.to_string()));
+    }
+    Ok((dir, count))
 }
 
-fn solve1(fname: &str) -> u32 {
-    let file = File::open(fname).unwrap();
-    let lines = BufReader::new(file).lines();
+fn read_motions(fname: &str) -> Vec<(Direction, u32)> {
+    read_lines(fname, parse_motion)
+}
 
-    let mut trail: HashSet<Coord> = HashSet::new();
-    let mut head = (0, 0);
-    let mut tail = (0, 0);
+/// every line of `fname` through `parse`, blank lines are skipped. Exits
+/// with the line number on anything that doesn't parse.
+fn read_lines<T>(fname: &str, parse: impl Fn(&str) -> Result<T, ParseError>) -> Vec<T> {
+    let file = File::open(fname).unwrap_or_else(|err| {
+        eprintln!("{}: {}", fname, err);
+        process::exit(1);
+    });
 
-    for line in lines {
+    let mut motions = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
         let line = line.expect("fatal error while reading file");
-        let new_trails;
-        (head, tail, new_trails) = parse_line_and_execute(head, tail, &line);
-        trail.extend(new_trails);
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse(&line) {
+            Ok(motion) => motions.push(motion),
+            Err(err) => {
+                eprintln!("{}:{}: {}", fname, i + 1, err);
+                process::exit(1);
+            }
+        }
+    }
+    motions
+}
+
+/// runs every motion in `fname` on a rope of `rope_len` knots, keeping
+/// track of where the `tracked` ones go
+fn simulate<R: FollowRule>(
+    fname: &str,
+    rope_len: usize,
+    rule: R,
+    tracked: impl IntoIterator<Item = usize>,
+) -> Rope<R> {
+    let motions = read_motions(fname);
+    let mut rope = Rope::for_motions(rope_len, rule, &motions).tracking(tracked);
+    for (dir, n) in motions {
+        rope.apply(&dir, n);
     }
+    rope
+}
 
-    trail.iter().count() as u32
+fn solve1(fname: &str) -> u32 {
+    solve2(fname, 2)
 }
 
 fn solve2(fname: &str, rope_len: usize) -> u32 {
-    let file = File::open(fname).unwrap();
-    let lines = BufReader::new(file).lines();
+    let rope = simulate(fname, rope_len, Taut, [rope_len - 1]);
+    rope.visited(rope_len - 1).len() as u32
+}
 
-    let mut trail: HashSet<Coord> = HashSet::new();
-    let mut rope = vec![(0, 0); rope_len];
+/// moves the head of `body` one step and lets every other knot follow the
+/// one in front of it
+fn move_rope_once(body: &mut [Coord], dir: &Direction, rule: &impl FollowRule) {
+    pull_rope(body, dir.step(body[0]), |leader, knot| {
+        rule.follow(leader, knot)
+    });
+}
 
-    for line in lines {
-        let line = line.expect("fatal error while reading file");
+/// Puts the head of `body` at `head` and lets every other knot follow the
+/// one in front of it by `follow`, whatever the knots' coordinates are.
+fn pull_rope<K: Copy>(body: &mut [K], head: K, follow: impl Fn(K, K) -> K) {
+    body[0] = head;
+    let mut leader = head;
 
-        let (dir, n) = parse_motion(&line);
+    for knot in body.iter_mut().skip(1) {
+        *knot = follow(leader, *knot);
+        leader = *knot;
+    }
+}
+
+/// how many cells every knot visited and where it ended up, head first
+fn report<R: FollowRule>(fname: &str, rope_len: usize, rule: R) {
+    let rope = simulate(fname, rope_len, rule, 0..rope_len);
+    for (i, knot) in rope.knots().iter().enumerate() {
+        println!(
+            "{}: {} cells, ends at ({}, {})",
+            i,
+            rope.visited(i).len(),
+            knot.0,
+            knot.1
+        );
+    }
+}
+
+/// random motions along rows and columns, at least `steps` steps in all
+fn generate_motions(steps: u64, longest: u32, seed: u64) -> Vec<(Direction, u32)> {
+    // xorshift64
+    let mut state = seed | 1;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
 
-        for _ in 0..n {
-            move_rope_once(&mut rope, &dir);
-            trail.insert(*rope.last().unwrap());
+    let mut motions = vec![];
+    let mut total = 0;
+    while total < steps {
+        let dir = [Up, Right, Down, Left][(next() % 4) as usize];
+        let n = 1 + (next() % longest as u64) as u32;
+        motions.push((dir, n));
+        total += n as u64;
+    }
+    motions
+}
+
+/// where the tail of a `rope_len` rope has been, the way it used to be done
+fn tail_visits_hashed(motions: &[(Direction, u32)], rope_len: usize) -> usize {
+    let mut trail = HashSet::new();
+    let mut rope = vec![(0, 0); rope_len];
+    trail.insert((0, 0));
+    for (dir, n) in motions {
+        for _ in 0..*n {
+            move_rope_once(&mut rope, dir, &Taut);
+            trail.insert(rope[rope_len - 1]);
         }
     }
+    trail.len()
+}
 
-    trail.into_iter().count() as u32
+/// the same with the bounds worked out first and a bitset
+fn tail_visits_dense(motions: &[(Direction, u32)], rope_len: usize) -> usize {
+    let mut trail = Trail::new(Bounds::of_motions((0, 0), motions));
+    let mut rope = vec![(0, 0); rope_len];
+    trail.insert((0, 0));
+    for (dir, n) in motions {
+        for _ in 0..*n {
+            move_rope_once(&mut rope, dir, &Taut);
+            trail.insert(rope[rope_len - 1]);
+        }
+    }
+    trail.len()
 }
 
-fn move_rope_once(body: &mut Vec<Coord>, dir: &Direction) {
-    (body[0], body[1]) = move_once(body[0], body[1], dir);
-    let mut head = body[1];
+fn bench(steps: u64) {
+    let start = Instant::now();
+    let motions = generate_motions(steps, 20, 2022);
+    let bounds = Bounds::of_motions((0, 0), &motions);
+    println!(
+        "generating {} steps: {:?}, {}x{} cells",
+        steps,
+        start.elapsed(),
+        bounds.width(),
+        bounds.height()
+    );
+
+    let start = Instant::now();
+    let hashed = tail_visits_hashed(&motions, 10);
+    let hashed_time = start.elapsed();
+    println!("hash set: {} cells in {:?}", hashed, hashed_time);
+
+    let start = Instant::now();
+    let dense = tail_visits_dense(&motions, 10);
+    let dense_time = start.elapsed();
+    println!("bitset: {} cells in {:?}", dense, dense_time);
+    assert_eq!(hashed, dense);
+    println!(
+        "{:.1}x faster",
+        hashed_time.as_secs_f64() / dense_time.as_secs_f64()
+    );
+
+    let start = Instant::now();
+    let mut rope = Rope::for_motions(10, Taut, &motions).tracking_tail();
+    for (dir, n) in &motions {
+        rope.apply(dir, *n);
+    }
+    println!(
+        "every knot tracked: {} cells for the tail in {:?}",
+        rope.visited(9).len(),
+        start.elapsed()
+    );
+
+    // long motions are where straightening out pays off, here sweeping
+    // back and forth along rows, then columns, so the box stays small
+    let sweeps = [
+        [(Right, 10000), (UpLeft, 7), (Left, 10000), (UpRight, 7)],
+        [(Up, 10000), (DownRight, 7), (Down, 10000), (UpRight, 7)],
+    ];
+    for (sweep, along) in sweeps.iter().zip(["rows", "columns"]) {
+        let mut motions = vec![];
+        while (motions.len() as u64 / 4) * 20014 < steps {
+            motions.extend(sweep);
+        }
+        bench_long_motions(&format!("long motions along {}", along), &motions);
+    }
 
-    for knot in body.iter_mut().skip(2) {
-        *knot = tail_follow_new_head(head, *knot);
-        head = *knot;
+    // and going round a square far too big for a bit per cell
+    let side = (steps / 4).clamp(1, i32::MAX as u64) as u32;
+    let square = [(Right, side), (Up, side), (Left, side), (Down, side)];
+    bench_long_motions("a square of long motions", &square);
+}
+
+/// how much faster fast-forwarding makes `motions` than going step by step
+fn bench_long_motions(name: &str, motions: &[(Direction, u32)]) {
+    let bounds = Bounds::of_motions((0, 0), motions);
+    let start = Instant::now();
+    let stepped = tail_visits_dense(motions, 10);
+    let stepped_time = start.elapsed();
+    println!(
+        "{}, {}x{} cells, step by step: {} cells in {:?}",
+        name,
+        bounds.width(),
+        bounds.height(),
+        stepped,
+        stepped_time
+    );
+
+    let start = Instant::now();
+    let mut rope = Rope::for_motions(10, Taut, motions).tracking_tail();
+    for (dir, n) in motions {
+        rope.apply(dir, *n);
     }
+    let fast_time = start.elapsed();
+    println!(
+        "{}, fast-forwarded: {} cells in {:?}, {:.1}x faster",
+        name,
+        rope.visited(9).len(),
+        fast_time,
+        stepped_time.as_secs_f64() / fast_time.as_secs_f64()
+    );
+    assert_eq!(stepped, rope.visited(9).len());
 }
 
-fn main() {
-    let x = solve1("input.txt");
-    println!("{}", x);
-    let x = solve2("input.txt", 10);
-    println!("{}", x);
+/// `file` or `file@x,y` for a rope starting somewhere else than the origin
+fn parse_script(arg: &str, knots: usize) -> multi::RopeScript {
+    let (fname, start) = match arg.rsplit_once('@') {
+        Some((fname, start)) => {
+            let start = start
+                .split_once(',')
+                .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)));
+            match start {
+                Some(start) => (fname, start),
+                None => {
+                    eprintln!("{}: the start should look like x,y", arg);
+                    process::exit(1);
+                }
+            }
+        }
+        None => (arg, (0, 0)),
+    };
+    multi::RopeScript {
+        start,
+        knots,
+        motions: read_motions(fname),
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::{are_adjacent, move_once, parse_line_and_execute, parse_motion, solve1, solve2};
+/// runs several ropes at once, see `multi`
+fn run_ropes(args: &[&str]) {
+    let mut mode = multi::CollisionMode::Report;
+    let mut knots = 10;
+    let mut scripts = vec![];
+
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        match arg {
+            "--collisions" => {
+                mode = match args.next() {
+                    Some(&"ignore") => multi::CollisionMode::Ignore,
+                    Some(&"stop") => multi::CollisionMode::Stop,
+                    Some(&"report") => multi::CollisionMode::Report,
+                    _ => {
+                        eprintln!("--collisions takes ignore, stop or report");
+                        process::exit(1);
+                    }
+                }
+            }
+            "--knots" => knots = parse_knots(args.next().copied().unwrap_or("")),
+            _ => scripts.push(parse_script(arg, knots)),
+        }
+    }
 
-    #[test]
-    fn test_parse_line_and_execute() {
-        let tests = [
-            ((0, 0), (0, 0), "R 1", (1, 0), (0, 0)),
-            ((4, 0), (3, 0), "U 4", (4, 4), (4, 3)),
-            ((4, 4), (4, 3), "L 3", (1, 4), (2, 4)),
-            ((1, 4), (2, 4), "D 1", (1, 3), (2, 4)),
-            ((1, 3), (2, 4), "R 4", (5, 3), (4, 3)),
-            ((5, 3), (4, 3), "D 1", (5, 2), (4, 3)),
-            ((5, 2), (4, 3), "L 5", (0, 2), (1, 2)),
-            ((0, 2), (1, 2), "R 2", (2, 2), (1, 2)),
-        ];
+    let outcome = multi::simulate(&scripts, &Taut, mode);
+    for collision in &outcome.collisions {
+        println!("{}", collision);
+    }
+    if outcome.stopped {
+        println!("stopped after {} steps", outcome.steps);
+    } else {
+        println!("{} steps", outcome.steps);
+    }
+    for (i, (rope, tail)) in outcome.ropes.iter().zip(&outcome.tails).enumerate() {
+        let head = rope.first().copied().unwrap_or_default();
+        println!(
+            "rope {}: head at ({}, {}), the tail visited {} cells",
+            i,
+            head.0,
+            head.1,
+            tail.len()
+        );
+    }
+    println!("{} cells visited in all", outcome.visited.len());
+}
 
-        for test in tests {
-            let head = test.0;
-            let tail = test.1;
-            let line = test.2;
-            let (head, tail, _) = parse_line_and_execute(head, tail, line);
-            assert_eq!(head, test.3);
-            assert_eq!(tail, test.4);
+fn parse_knots(knots: &str) -> usize {
+    match knots.parse() {
+        Ok(knots) if knots > 0 => knots,
+        _ => {
+            eprintln!("{}: not a number of knots", knots);
+            process::exit(1);
         }
     }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        ["bench"] => bench(5_000_000),
+        ["bench", steps] => bench(steps.parse().expect("steps should be a number")),
+        ["multi", ref rest @ ..] if !rest.is_empty() => run_ropes(rest),
+        [] | [_] => {
+            let fname = args.first().copied().unwrap_or("input.txt");
+            let x = solve1(fname);
+            println!("{}", x);
+            let x = solve2(fname, 10);
+            println!("{}", x);
+        }
+        ["animate", fname, knots, ref out @ ..] => {
+            let motions = read_motions(fname);
+            let knots = parse_knots(knots);
+            let frames = render::animate(&motions, Rope::for_motions(knots, Taut, &motions), true);
+            match out {
+                [] => {
+                    for frame in frames {
+                        print!("{}", render::frame_to_string(&frame));
+                    }
+                }
+                ["--dir", dir] => render::write_frames_to_dir(frames, Path::new(dir))
+                    .expect("failed to write frames"),
+                ["--file", path] => fs::write(path, render::frames_to_string(frames))
+                    .expect("failed to write frames"),
+                _ => eprintln!("write frames with --dir <dir> or --file <file>"),
+            }
+        }
+        ["3d", fname, knots] => {
+            let motions = read_lines(fname, nd::parse_motion_3d);
+            let knots = parse_knots(knots);
+            for (i, ([x, y, z], cells)) in nd::run(knots, &motions).iter().enumerate() {
+                println!("{}: {} cells, ends at ({}, {}, {})", i, cells, x, y, z);
+            }
+        }
+        ["stats", fname, knots, ref out @ ..] => {
+            let motions = read_motions(fname);
+            let rope = Rope::for_motions(parse_knots(knots), Taut, &motions);
+            let replay = stats::replay(&motions, rope);
+            match out {
+                [] => print!("{}", replay.to_csv()),
+                ["--json"] => print!("{}", replay.to_json()),
+                ["--visits"] => print!("{}", replay.visits_to_csv()),
+                ["--heatmaps", dir] => {
+                    fs::create_dir_all(dir).expect("failed to create the directory");
+                    for i in 0..replay.knots.len() {
+                        let path = Path::new(dir).join(format!("knot_{}.pgm", i));
+                        let heatmap = replay.heatmap(i).unwrap_or_else(|err| {
+                            eprintln!("{}", err);
+                            process::exit(1);
+                        });
+                        fs::write(path, heatmap).expect("failed to write the heatmap");
+                    }
+                }
+                _ => eprintln!("stats are CSV, or --json, --visits or --heatmaps <dir>"),
+            }
+        }
+        ["render", fname, knots] => {
+            // the last frame of `animate`, without the ones before it
+            let motions = read_motions(fname);
+            let mut rope = Rope::for_motions(parse_knots(knots), Taut, &motions).tracking_tail();
+            for (dir, n) in &motions {
+                rope.apply(dir, *n);
+            }
+            let tail = rope.knots().len() - 1;
+            let view = Bounds::of_motions((0, 0), &motions);
+            print!(
+                "{}",
+                render::frame(rope.knots(), Some(rope.visited(tail)), &view)
+            );
+        }
+        [fname, knots, rule] => {
+            let knots = parse_knots(knots);
+            match rule.split_once('=') {
+                None if rule == "taut" => report(fname, knots, Taut),
+                None if rule == "orthogonal" => report(fname, knots, Orthogonal),
+                Some(("elastic", length)) => match length.parse() {
+                    Ok(length) => report(fname, knots, Elastic(length)),
+                    Err(_) => eprintln!("{}: not a length", length),
+                },
+                _ => eprintln!("rules are taut, orthogonal and elastic=<length>"),
+            }
+        }
+        _ => {
+            eprintln!("usage: day9 [input file]");
+            eprintln!("       day9 <input file> <knots> <taut|orthogonal|elastic=length>");
+            eprintln!("       day9 render <input file> <knots>");
+            eprintln!("       day9 3d <input file> <knots>");
+            eprintln!(
+                "       day9 stats <input file> <knots> [--json | --visits | --heatmaps <dir>]"
+            );
+            eprintln!("       day9 bench [steps]");
+            eprintln!(
+                "       day9 multi [--collisions ignore|stop|report] [--knots n] <input file[@x,y]>..."
+            );
+            eprintln!("       day9 animate <input file> <knots> [--dir <dir> | --file <file>]");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        are_adjacent, generate_motions, move_rope_once, parse_motion, solve1, solve2,
+        tail_follow_new_head, tail_visits_dense, tail_visits_hashed, Rope, Taut,
+    };
 
     #[test]
-    fn test_move_once() {
+    fn test_move_rope_once() {
         use crate::Direction::*;
         let tests = [
             // general
@@ -186,29 +597,50 @@ mod tests {
             ((0, 0), (1, 1), Right, (1, 0), (1, 1)),
             ((0, 0), (1, 1), Up, (0, 1), (1, 1)),
             ((1, 1), (0, 0), Down, (1, 0), (0, 0)),
+            // diagonals
+            ((0, 0), (0, 0), UpRight, (1, 1), (0, 0)),
+            ((1, 1), (0, 0), UpRight, (2, 2), (1, 1)),
+            ((1, 0), (0, 0), UpRight, (2, 1), (1, 1)),
+            ((0, 0), (0, 0), DownLeft, (-1, -1), (0, 0)),
+            ((0, 1), (0, 0), DownRight, (1, 0), (0, 0)),
+            ((-1, 0), (0, 0), UpLeft, (-2, 1), (-1, 1)),
         ];
 
         for test in tests {
-            let head = test.0;
-            let tail = test.1;
-            let dir = test.2;
-            assert_eq!(move_once(head, tail, &dir), (test.3, test.4));
+            let mut body = [test.0, test.1];
+            move_rope_once(&mut body, &test.2, &Taut);
+            assert_eq!(body, [test.3, test.4]);
         }
     }
 
     #[test]
     fn test_are_adjacent() {
         let tests = [
-            ((0, 1), (0, 0), true),
-            ((-5, 9), (-6, 10), true),
-            ((2, 0), (0, 0), false),
-            ((5, 8), (7, 8), false),
-            ((-1, -1), (1, 1), false),
+            ([0, 1], [0, 0], true),
+            ([-5, 9], [-6, 10], true),
+            ([2, 0], [0, 0], false),
+            ([5, 8], [7, 8], false),
+            ([-1, -1], [1, 1], false),
         ];
 
         for test in tests {
             assert_eq!(are_adjacent(test.0, test.1), test.2);
         }
+
+        assert!(are_adjacent([1, -1, 1], [0, 0, 0]));
+        assert!(!are_adjacent([1, 1, 2], [0, 0, 0]));
+        assert!(are_adjacent([7], [6]));
+    }
+
+    #[test]
+    fn test_tail_follow_new_head() {
+        assert_eq!(tail_follow_new_head([2, 1], [0, 0]), [1, 1]);
+        assert_eq!(tail_follow_new_head([1, 1, 1], [0, 0, 0]), [0, 0, 0]);
+        assert_eq!(tail_follow_new_head([2, 1, -1], [0, 0, 0]), [1, 1, -1]);
+        assert_eq!(
+            tail_follow_new_head([0, 0, 3, 0], [0, 0, 1, 0]),
+            [0, 0, 2, 0]
+        );
     }
 
     #[test]
@@ -219,10 +651,67 @@ mod tests {
             ("L 3", Left, 3),
             ("U 4", Up, 4),
             ("D 2", Down, 2),
+            ("UR 12", UpRight, 12),
+            ("RU 3", UpRight, 3),
+            ("DL 100", DownLeft, 100),
+            ("LU\t7\r", UpLeft, 7),
+            ("  RD   4096  ", DownRight, 4096),
+            ("L 2147483647", Left, 2147483647),
+        ];
+
+        for test in tests {
+            assert_eq!(parse_motion(test.0), Ok((test.1, test.2)));
+        }
+    }
+
+    #[test]
+    fn test_parse_motion_errors() {
+        let tests = [
+            ("", "\"\" is not a motion"),
+            ("R", "\"R\" is not a motion"),
+            ("R 1 2", "\"R 1 2\" is not a motion"),
+            ("X 1", "\"X\" is not a direction"),
+            ("UD 1", "\"UD\" is not a direction"),
+            ("R -1", "\"-1\" is not a number of steps"),
+            ("R 99999999999", "\"99999999999\" is not a number of steps"),
+            (
+                "R 2147483648",
+                "2147483648 steps is over the most there can be, 2147483647",
+            ),
         ];
 
         for test in tests {
-            assert_eq!(parse_motion(test.0), (test.1, test.2));
+            assert_eq!(parse_motion(test.0).unwrap_err().to_string(), test.1);
+        }
+    }
+
+    #[test]
+    fn test_dense_matches_hashed() {
+        for seed in 0..10 {
+            let motions = generate_motions(2000, 20, seed);
+            for rope_len in [1, 2, 10] {
+                assert_eq!(
+                    tail_visits_dense(&motions, rope_len),
+                    tail_visits_hashed(&motions, rope_len)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_forward_matches_stepping() {
+        for seed in 0..10 {
+            let motions = generate_motions(5000, 200, seed);
+            for rope_len in [1, 2, 10] {
+                let mut rope = Rope::for_motions(rope_len, Taut, &motions).tracking_tail();
+                for (dir, n) in &motions {
+                    rope.apply(dir, *n);
+                }
+                assert_eq!(
+                    rope.visited(rope_len - 1).len(),
+                    tail_visits_dense(&motions, rope_len)
+                );
+            }
         }
     }
 
@@ -0,0 +1,300 @@
+//! Several ropes on the same plane, each following its own motions.
+//!
+//! All ropes take their steps together: on every step each rope that still
+//! has motions left moves its head once, the others stay where they are.
+//! Two knots of different ropes collide when they end a step in the same
+//! cell. Knots swapping places in the middle of a step don't count.
+
+use crate::{
+    move_rope_once,
+    rope::FollowRule,
+    trail::{Bounds, Trail},
+    Coord, Direction,
+};
+use std::{collections::HashMap, fmt::Display};
+
+/// one rope, where it starts and what its head does
+pub struct RopeScript {
+    pub start: Coord,
+    pub knots: usize,
+    pub motions: Vec<(Direction, u32)>,
+}
+
+/// what to do when knots of different ropes meet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionMode {
+    /// don't even look
+    Ignore,
+    /// note the collisions of the first step that has any and stop there
+    Stop,
+    /// note every collision and carry on
+    Report,
+}
+
+/// A knot of one rope in the same cell as a knot of another. Ropes and
+/// knots are `(rope, knot)` indices, the rope listed first has the lower
+/// index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision {
+    /// 0 is before anything moved
+    pub step: u64,
+    pub cell: Coord,
+    pub first: (usize, usize),
+    pub second: (usize, usize),
+}
+
+impl Display for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "step {}: rope {} knot {} and rope {} knot {} at ({}, {})",
+            self.step,
+            self.first.0,
+            self.first.1,
+            self.second.0,
+            self.second.1,
+            self.cell.0,
+            self.cell.1
+        )
+    }
+}
+
+pub struct Outcome {
+    /// every rope's knots when the simulation ended, head first
+    pub ropes: Vec<Vec<Coord>>,
+    pub steps: u64,
+    pub collisions: Vec<Collision>,
+    /// whether a collision ended things early
+    pub stopped: bool,
+    /// every cell any knot of any rope has been in
+    pub visited: Trail,
+    /// where each rope's tail has been
+    pub tails: Vec<Trail>,
+}
+
+/// the collisions between knots of different ropes as they are now
+fn find_collisions(ropes: &[Vec<Coord>], step: u64) -> Vec<Collision> {
+    let mut occupied: HashMap<Coord, (usize, usize)> = HashMap::new();
+    let mut collisions = vec![];
+
+    for (r, rope) in ropes.iter().enumerate() {
+        let mut seen_here = vec![];
+        for (k, &knot) in rope.iter().enumerate() {
+            match occupied.get(&knot) {
+                Some(&first) if first.0 != r => collisions.push(Collision {
+                    step,
+                    cell: knot,
+                    first,
+                    second: (r, k),
+                }),
+                Some(_) => (),
+                None => seen_here.push((knot, (r, k))),
+            }
+        }
+        // only added now, a rope's own knots never collide, and the knot
+        // nearest the head stands for the rest
+        for (cell, knot) in seen_here {
+            occupied.entry(cell).or_insert(knot);
+        }
+    }
+
+    collisions
+}
+
+/// runs every script together until they've all finished, or until the
+/// first collision with `CollisionMode::Stop`
+pub fn simulate(scripts: &[RopeScript], rule: &impl FollowRule, mode: CollisionMode) -> Outcome {
+    let mut bounds = Bounds::fit(scripts.iter().map(|script| script.start));
+    for script in scripts {
+        let rope_bounds = Bounds::of_motions(script.start, &script.motions);
+        bounds.include(rope_bounds.min);
+        bounds.include(rope_bounds.max);
+    }
+
+    let mut ropes: Vec<Vec<Coord>> = scripts
+        .iter()
+        .map(|script| vec![script.start; script.knots])
+        .collect();
+    let mut moves: Vec<Box<dyn Iterator<Item = Direction> + '_>> = scripts
+        .iter()
+        .map(|script| {
+            // repeat_n would need Rust 1.82
+            #[allow(clippy::manual_repeat_n)]
+            let steps = script
+                .motions
+                .iter()
+                .flat_map(|&(dir, n)| std::iter::repeat(dir).take(n as usize));
+            Box::new(steps) as Box<dyn Iterator<Item = Direction>>
+        })
+        .collect();
+
+    let mut visited = Trail::new(bounds);
+    let mut tails = vec![Trail::new(bounds); scripts.len()];
+    let mut collisions = vec![];
+    let mut step = 0;
+    let mut stopped = false;
+
+    loop {
+        for (rope, tail) in ropes.iter().zip(&mut tails) {
+            for &knot in rope {
+                visited.insert(knot);
+            }
+            if let Some(&last) = rope.last() {
+                tail.insert(last);
+            }
+        }
+
+        if mode != CollisionMode::Ignore {
+            let found = find_collisions(&ropes, step);
+            if mode == CollisionMode::Stop && !found.is_empty() {
+                collisions = found;
+                stopped = true;
+                break;
+            }
+            collisions.extend(found);
+        }
+
+        let mut moved = false;
+        for (rope, steps) in ropes.iter_mut().zip(&mut moves) {
+            if rope.is_empty() {
+                continue;
+            }
+            if let Some(dir) = steps.next() {
+                move_rope_once(rope, &dir, rule);
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+        step += 1;
+    }
+
+    Outcome {
+        ropes,
+        steps: step,
+        collisions,
+        stopped,
+        visited,
+        tails,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{simulate, CollisionMode, RopeScript};
+    use crate::{read_motions, rope::Taut, Direction::*};
+
+    fn head_on() -> Vec<RopeScript> {
+        vec![
+            RopeScript {
+                start: (0, 0),
+                knots: 2,
+                motions: vec![(Right, 10)],
+            },
+            RopeScript {
+                start: (10, 0),
+                knots: 2,
+                motions: vec![(Left, 10)],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_single_rope() {
+        let script = RopeScript {
+            start: (0, 0),
+            knots: 10,
+            motions: read_motions("test_input2.txt"),
+        };
+        let outcome = simulate(&[script], &Taut, CollisionMode::Report);
+
+        assert_eq!(outcome.tails[0].len(), 36);
+        assert!(outcome.collisions.is_empty());
+        assert_eq!(outcome.steps, 96);
+    }
+
+    #[test]
+    fn test_ignore() {
+        let outcome = simulate(&head_on(), &Taut, CollisionMode::Ignore);
+
+        assert!(outcome.collisions.is_empty());
+        assert!(!outcome.stopped);
+        assert_eq!(outcome.steps, 10);
+        assert_eq!(outcome.ropes, [[(10, 0), (9, 0)], [(0, 0), (1, 0)]]);
+        // the whole row between the starts
+        assert_eq!(outcome.visited.len(), 11);
+        assert_eq!(outcome.tails[0].len(), 10);
+    }
+
+    #[test]
+    fn test_stop() {
+        let outcome = simulate(&head_on(), &Taut, CollisionMode::Stop);
+
+        assert!(outcome.stopped);
+        assert_eq!(outcome.steps, 5);
+        assert_eq!(outcome.ropes, [[(5, 0), (4, 0)], [(5, 0), (6, 0)]]);
+        assert_eq!(
+            outcome
+                .collisions
+                .iter()
+                .map(|collision| collision.to_string())
+                .collect::<Vec<_>>(),
+            ["step 5: rope 0 knot 0 and rope 1 knot 0 at (5, 0)"]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let outcome = simulate(&head_on(), &Taut, CollisionMode::Report);
+        let collisions: Vec<_> = outcome
+            .collisions
+            .iter()
+            .map(|collision| (collision.step, collision.first, collision.second))
+            .collect();
+
+        // the heads meet, then the tails
+        assert_eq!(collisions, [(5, (0, 0), (1, 0)), (6, (0, 1), (1, 1)),]);
+        assert!(!outcome.stopped);
+        assert_eq!(outcome.steps, 10);
+    }
+
+    #[test]
+    fn test_stacked_start() {
+        let mut scripts = head_on();
+        scripts[1].start = (0, 0);
+        let outcome = simulate(&scripts, &Taut, CollisionMode::Stop);
+
+        assert_eq!(outcome.steps, 0);
+        assert_eq!(
+            outcome.collisions[0].to_string(),
+            "step 0: rope 0 knot 0 and rope 1 knot 0 at (0, 0)"
+        );
+        assert_eq!(outcome.collisions.len(), 2);
+    }
+
+    #[test]
+    fn test_scripts_of_different_lengths() {
+        let scripts = [
+            RopeScript {
+                start: (0, 0),
+                knots: 1,
+                motions: vec![(Up, 3)],
+            },
+            RopeScript {
+                start: (5, 5),
+                knots: 3,
+                motions: vec![(Down, 2), (Left, 5), (DownLeft, 1)],
+            },
+        ];
+        let outcome = simulate(&scripts, &Taut, CollisionMode::Report);
+
+        assert_eq!(outcome.steps, 8);
+        assert_eq!(outcome.ropes[0], [(0, 3)]);
+        assert_eq!(outcome.ropes[1][0], (-1, 2));
+        // the second head runs into the first rope, which has stopped
+        assert_eq!(outcome.collisions.len(), 1);
+        assert_eq!(outcome.collisions[0].step, 7);
+        assert_eq!(outcome.collisions[0].cell, (0, 3));
+    }
+}
@@ -0,0 +1,114 @@
+//! Ropes whose knots have any number of coordinates, following the
+//! puzzle's rule along every axis at once. Only counting cells, the rest
+//! is `rope::Rope`'s in the plane.
+//!
+//! Motions in 3D add `F` and `B` for forwards and backwards along the third
+//! axis to the plane's directions, which stay flat.
+
+use crate::{
+    parse_motion_with, pull_rope, tail_follow_new_head, trail::axis_end, Direction, ParseError,
+};
+use std::collections::HashSet;
+
+pub type Point<const N: usize> = [i32; N];
+
+/// how one step along `token` changes the coordinates in 3D
+pub fn direction_3d(token: &str) -> Option<Point<3>> {
+    match token {
+        "F" => Some([0, 0, 1]),
+        "B" => Some([0, 0, -1]),
+        _ => Direction::from_token(token).map(|dir| {
+            let (x, y) = dir.vector();
+            [x, y, 0]
+        }),
+    }
+}
+
+pub fn parse_motion_3d(line: &str) -> Result<(Point<3>, u32), ParseError> {
+    parse_motion_with(line, direction_3d)
+}
+
+/// Runs `motions` on `len` knots starting at the origin, each one
+/// following the one in front the puzzle's way. Where every knot ends up
+/// and how many cells it has been in, head first. The cells are hashed
+/// since the space they cover isn't known. Panics if a motion takes the
+/// head further than a coordinate goes.
+pub fn run<const N: usize>(len: usize, motions: &[(Point<N>, u32)]) -> Vec<(Point<N>, usize)> {
+    assert!(len > 0, "a rope needs at least one knot");
+    let mut knots = vec![[0; N]; len];
+    let mut visited = vec![HashSet::from([[0; N]]); len];
+
+    for &(vector, n) in motions {
+        // panics unless the head can get to the end of the motion, then no
+        // step on the way there overflows
+        for (&from, &step) in knots[0].iter().zip(&vector) {
+            axis_end(from, step, n);
+        }
+        for _ in 0..n {
+            let head = std::array::from_fn(|axis| knots[0][axis] + vector[axis]);
+            pull_rope(&mut knots, head, tail_follow_new_head);
+            for (visited, &knot) in visited.iter_mut().zip(&knots) {
+                visited.insert(knot);
+            }
+        }
+    }
+    knots
+        .into_iter()
+        .zip(visited.iter().map(HashSet::len))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_motion_3d, run};
+    use crate::{read_lines, read_motions, solve2, ParseError};
+
+    #[test]
+    fn test_parse_motion_3d() {
+        assert_eq!(parse_motion_3d("F 3"), Ok(([0, 0, 1], 3)));
+        assert_eq!(parse_motion_3d("B 1"), Ok(([0, 0, -1], 1)));
+        assert_eq!(parse_motion_3d("UL 2"), Ok(([-1, 1, 0], 2)));
+        assert_eq!(
+            parse_motion_3d("X 2"),
+            Err(ParseError::UnknownDirection("X".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_3d() {
+        let motions: Vec<_> = ["F 3", "U 1", "R 1"]
+            .iter()
+            .map(|line| parse_motion_3d(line).unwrap())
+            .collect();
+        // still touching, if only just
+        let knots: Vec<_> = run(2, &motions).into_iter().map(|(knot, _)| knot).collect();
+        assert_eq!(knots, [[1, 1, 3], [0, 0, 2]]);
+
+        // then it has to move along all three axes at once
+        let mut motions = motions;
+        motions.push(([1, 0, 0], 1));
+        assert_eq!(run(2, &motions)[1], ([1, 1, 3], 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "further than a coordinate can")]
+    fn test_too_far() {
+        run(2, &[([0, 1, 1], 5), ([0, 0, 1], i32::MAX as u32)]);
+    }
+
+    #[test]
+    fn test_flat_motions_match_the_plane() {
+        for fname in ["test_input.txt", "test_input2.txt"] {
+            let motions = read_lines(fname, parse_motion_3d);
+            let knots = run(10, &motions);
+            let flat: Vec<_> = read_motions(fname)
+                .iter()
+                .map(|(dir, n)| (<[i32; 2]>::from(dir.vector()), *n))
+                .collect();
+
+            assert_eq!(knots[9].1, solve2(fname, 10) as usize);
+            assert_eq!(run(10, &flat)[9].1, knots[9].1);
+            assert_eq!(knots[1].1, solve2(fname, 2) as usize);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Drawing the rope the way the puzzle does, one frame per step.
+//!
+//! The head is `H` and the other knots are numbered from `1`, except in a
+//! rope of two where the tail is `T`. A knot hides the ones behind it and
+//! they all hide the start, `s`. Cells the tail has been in are `#`.
+
+use crate::{
+    rope::{FollowRule, Rope},
+    trail::{Bounds, Trail},
+    Coord, Direction,
+};
+use std::{fs, io, path::Path};
+
+/// how knot `i` of a rope of `len` knots is drawn
+fn label(i: usize, len: usize) -> char {
+    match i {
+        0 => 'H',
+        1 if len == 2 => 'T',
+        1..=9 => (b'0' + i as u8) as char,
+        // past 9 there's nothing sensible left
+        _ => '*',
+    }
+}
+
+/// Draws `knots`, head first, and `trail` inside `view`. Rows go from the
+/// top down, so `y` grows upwards like in the puzzle.
+pub fn frame(knots: &[Coord], trail: Option<&Trail>, view: &Bounds) -> String {
+    let (w, h) = (view.width(), view.height());
+    let mut cells = vec![b'.'; w * h];
+    let mut draw = |cell: Coord, chr: u8| {
+        if view.contains(cell) {
+            let (x, y) = (
+                (cell.0 - view.min.0) as usize,
+                (view.max.1 - cell.1) as usize,
+            );
+            cells[y * w + x] = chr;
+        }
+    };
+
+    for cell in trail.into_iter().flat_map(Trail::iter) {
+        draw(cell, b'#');
+    }
+    draw((0, 0), b's');
+    // the ones in front go on top
+    for (i, &knot) in knots.iter().enumerate().rev() {
+        draw(knot, label(i, knots.len()) as u8);
+    }
+
+    let mut out = String::with_capacity((w + 1) * h);
+    for row in cells.chunks(w) {
+        out.push_str(std::str::from_utf8(row).unwrap());
+        out.push('\n');
+    }
+    out
+}
+
+pub struct Frame {
+    pub title: String,
+    pub picture: String,
+}
+
+/// Frames of a rope running through some motions, drawn one at a time as
+/// they're asked for. See `animate`.
+pub struct Animation<'a, R: FollowRule> {
+    motions: &'a [(Direction, u32)],
+    rope: Rope<R>,
+    view: Bounds,
+    /// where the tail has been, if it's drawn
+    trail: Option<Trail>,
+    /// the motion being run and how many of its steps are done, nothing
+    /// before the first frame
+    at: Option<(usize, u32)>,
+    left: usize,
+}
+
+/// The rope before anything moves and after every single step of
+/// `motions`, all drawn in one viewport big enough for the whole run. No
+/// knot leaves the box the head and where they start from span, so that's
+/// the viewport.
+pub fn animate<R: FollowRule>(
+    motions: &[(Direction, u32)],
+    rope: Rope<R>,
+    show_trail: bool,
+) -> Animation<'_, R> {
+    let mut view = Bounds::of_motions(rope.knots()[0], motions);
+    for &knot in rope.knots() {
+        view.include(knot);
+    }
+    let steps: u64 = motions.iter().map(|&(_, n)| n as u64).sum();
+    Animation {
+        motions,
+        rope,
+        view,
+        trail: show_trail.then(|| Trail::new(view)),
+        at: None,
+        left: steps as usize + 1,
+    }
+}
+
+impl<R: FollowRule> Iterator for Animation<'_, R> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        let title = match self.at {
+            None => {
+                self.at = Some((0, 0));
+                "Initial State".to_string()
+            }
+            Some((mut motion, mut done)) => {
+                // motions of no steps have no frames
+                while self.motions.get(motion)?.1 == done {
+                    (motion, done) = (motion + 1, 0);
+                }
+                let (dir, n) = &self.motions[motion];
+                self.rope.step(dir);
+                self.at = Some((motion, done + 1));
+                format!("{} {} ({}/{})", dir.token(), n, done + 1, n)
+            }
+        };
+
+        let knots = self.rope.knots();
+        if let Some(trail) = &mut self.trail {
+            trail.insert(knots[knots.len() - 1]);
+        }
+        self.left -= 1;
+        Some(Frame {
+            title,
+            picture: frame(knots, self.trail.as_ref(), &self.view),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl<R: FollowRule> ExactSizeIterator for Animation<'_, R> {}
+
+/// one file per frame in `dir`, numbered so they sort in order
+pub fn write_frames_to_dir(
+    frames: impl ExactSizeIterator<Item = Frame>,
+    dir: &Path,
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let digits = frames.len().to_string().len();
+    for (i, frame) in frames.enumerate() {
+        let name = format!("frame_{:0width$}.txt", i, width = digits);
+        fs::write(dir.join(name), &frame.picture)?;
+    }
+    Ok(())
+}
+
+/// a frame under a `== title ==` line, the puzzle's own layout
+pub fn frame_to_string(frame: &Frame) -> String {
+    format!("== {} ==\n\n{}\n", frame.title, frame.picture)
+}
+
+/// every frame the way `frame_to_string` writes it
+pub fn frames_to_string(frames: impl IntoIterator<Item = Frame>) -> String {
+    frames
+        .into_iter()
+        .map(|frame| frame_to_string(&frame))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{animate, frame, frames_to_string};
+    use crate::{
+        read_motions,
+        rope::{Rope, Taut},
+        trail::Bounds,
+    };
+
+    #[test]
+    fn test_two_knots() {
+        let motions = read_motions("test_input.txt");
+        let frames = animate(&motions, Rope::for_motions(2, Taut, &motions), false);
+        assert_eq!(frames.len(), 25);
+        let frames: Vec<_> = frames.collect();
+
+        assert_eq!(
+            frames[0].picture,
+            "......\n......\n......\n......\nH.....\n"
+        );
+        assert_eq!(frames[1].title, "R 4 (1/4)");
+        assert_eq!(
+            frames[1].picture,
+            "......\n......\n......\n......\nTH....\n"
+        );
+        // after U 4
+        assert_eq!(
+            frames[8].picture,
+            "....H.\n....T.\n......\n......\ns.....\n"
+        );
+    }
+
+    #[test]
+    fn test_trail() {
+        let motions = read_motions("test_input.txt");
+        let frames = animate(&motions, Rope::for_motions(2, Taut, &motions), true);
+
+        assert_eq!(
+            frames.last().unwrap().picture,
+            "..##..\n...##.\n.TH##.\n....#.\ns###..\n"
+        );
+
+        // the start hides the trail
+        let trail = Rope::for_motions(2, Taut, &motions).tracking_tail();
+        let view = Bounds::fit([(5, 4)]);
+        assert_eq!(
+            frame(&[], Some(trail.visited(1)), &view),
+            "......\n......\n......\n......\ns.....\n"
+        );
+    }
+
+    #[test]
+    fn test_ten_knots() {
+        let motions = read_motions("test_input.txt");
+        let frames: Vec<_> =
+            animate(&motions, Rope::for_motions(10, Taut, &motions), false).collect();
+
+        assert_eq!(
+            frames[4].picture,
+            "......\n......\n......\n......\n4321H.\n"
+        );
+        assert_eq!(
+            frames[8].picture,
+            "....H.\n....1.\n..432.\n.5....\n6.....\n"
+        );
+    }
+
+    #[test]
+    fn test_frames_to_string() {
+        let motions = read_motions("test_input.txt");
+        let frames = animate(&motions[..1], Rope::for_motions(2, Taut, &motions), false);
+        let text = frames_to_string(frames.take(2));
+
+        assert_eq!(
+            text,
+            "== Initial State ==\n\nH....\n\n== R 4 (1/4) ==\n\nTH...\n\n"
+        );
+    }
+}
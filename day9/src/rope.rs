@@ -0,0 +1,334 @@
+//! A rope of any number of knots, with the way knots follow each other
+//! left open.
+
+use crate::{
+    are_adjacent, move_rope_once, tail_follow_new_head,
+    trail::{line_end, Bounds, Trail},
+    Coord, Direction,
+};
+
+/// How a knot catches up with the knot in front of it. Only where the two
+/// are relative to each other may matter, `Rope::apply` counts on that.
+pub trait FollowRule {
+    /// where `knot` ends up after the knot in front of it moved to `leader`
+    fn follow(&self, leader: Coord, knot: Coord) -> Coord;
+}
+
+/// The puzzle's rule: a knot stays put while it touches the one in front,
+/// diagonals included, otherwise it takes one step towards it, diagonally
+/// if they're in different rows and columns.
+pub struct Taut;
+
+impl FollowRule for Taut {
+    fn follow(&self, leader: Coord, knot: Coord) -> Coord {
+        let [x, y] = tail_follow_new_head([leader.0, leader.1], [knot.0, knot.1]);
+        (x, y)
+    }
+}
+
+/// Like `Taut` but knots can't move diagonally. A knot that has to move
+/// closes the bigger of the two gaps, the horizontal one on a tie.
+pub struct Orthogonal;
+
+impl FollowRule for Orthogonal {
+    fn follow(&self, leader: Coord, knot: Coord) -> Coord {
+        if are_adjacent([leader.0, leader.1], [knot.0, knot.1]) {
+            return knot;
+        }
+
+        let (dx, dy) = (leader.0 - knot.0, leader.1 - knot.1);
+        if dx.abs() >= dy.abs() {
+            (knot.0 + dx.signum(), knot.1)
+        } else {
+            (knot.0, knot.1 + dy.signum())
+        }
+    }
+}
+
+/// Knots can be up to `length` apart in either direction before they
+/// start following, `Elastic(1)` is the same as `Taut`.
+pub struct Elastic(pub u32);
+
+impl FollowRule for Elastic {
+    fn follow(&self, leader: Coord, knot: Coord) -> Coord {
+        if leader.0.abs_diff(knot.0) <= self.0 && leader.1.abs_diff(knot.1) <= self.0 {
+            return knot;
+        }
+        (
+            knot.0 + (leader.0 - knot.0).signum(),
+            knot.1 + (leader.1 - knot.1).signum(),
+        )
+    }
+}
+
+/// A rope whose first knot is the head. Can remember every cell some of
+/// its knots have been in, which all have to be inside the bounds it's
+/// made with.
+pub struct Rope<R: FollowRule> {
+    knots: Vec<Coord>,
+    rule: R,
+    bounds: Bounds,
+    /// for the knots being tracked
+    visited: Vec<Option<Trail>>,
+}
+
+impl<R: FollowRule> Rope<R> {
+    /// `len` knots, all starting at the origin
+    pub fn new(len: usize, rule: R, bounds: Bounds) -> Rope<R> {
+        Rope::from_knots(vec![(0, 0); len], rule, bounds)
+    }
+
+    /// `len` knots at the origin with room for all of `motions`
+    pub fn for_motions(len: usize, rule: R, motions: &[(Direction, u32)]) -> Rope<R> {
+        Rope::new(len, rule, Bounds::of_motions((0, 0), motions))
+    }
+
+    /// a rope with its knots wherever they're given, head first. The
+    /// bounds grow to hold them.
+    pub fn from_knots(knots: Vec<Coord>, rule: R, bounds: Bounds) -> Rope<R> {
+        assert!(!knots.is_empty(), "a rope needs at least one knot");
+        let mut bounds = bounds;
+        for &knot in &knots {
+            bounds.include(knot);
+        }
+        Rope {
+            visited: vec![None; knots.len()],
+            knots,
+            rule,
+            bounds,
+        }
+    }
+
+    /// the same rope remembering where each of `knots` goes from now on,
+    /// by index with 0 the head
+    pub fn tracking(mut self, knots: impl IntoIterator<Item = usize>) -> Rope<R> {
+        for i in knots {
+            let mut trail = Trail::new(self.bounds);
+            trail.insert(self.knots[i]);
+            self.visited[i] = Some(trail);
+        }
+        self
+    }
+
+    /// the same rope remembering where its last knot goes
+    pub fn tracking_tail(self) -> Rope<R> {
+        let tail = self.knots.len() - 1;
+        self.tracking([tail])
+    }
+
+    pub fn knots(&self) -> &[Coord] {
+        &self.knots
+    }
+
+    /// every cell knot `i` has been in since it's been tracked, 0 being
+    /// the head. Panics if it isn't.
+    pub fn visited(&self, i: usize) -> &Trail {
+        self.visited[i]
+            .as_ref()
+            .unwrap_or_else(|| panic!("knot {} isn't tracked", i))
+    }
+
+    /// moves the head one step and lets the rest follow
+    pub fn step(&mut self, dir: &Direction) {
+        move_rope_once(&mut self.knots, dir, &self.rule);
+        for (visited, &knot) in self.visited.iter_mut().zip(&self.knots) {
+            if let Some(visited) = visited {
+                visited.insert(knot);
+            }
+        }
+    }
+
+    /// Moves the head `n` steps. Once a step moves every knot the same way
+    /// as the head, the rope has straightened out behind it and every step
+    /// after that does exactly the same, so the rest of them are done in
+    /// one go, each knot's trail getting a straight line.
+    pub fn apply(&mut self, dir: &Direction, n: u32) {
+        let (dx, dy) = dir.vector();
+        let mut before = self.knots.clone();
+
+        for done in 1..=n {
+            self.step(dir);
+            let straight = self
+                .knots
+                .iter()
+                .zip(&before)
+                .all(|(knot, was)| *knot == (was.0 + dx, was.1 + dy));
+
+            if straight {
+                let rest = n - done;
+                for (knot, visited) in self.knots.iter_mut().zip(&mut self.visited) {
+                    if let Some(visited) = visited {
+                        visited.insert_line(*knot, (dx, dy), rest);
+                    }
+                    *knot = line_end(*knot, (dx, dy), rest);
+                }
+                return;
+            }
+            before.copy_from_slice(&self.knots);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Elastic, FollowRule, Orthogonal, Rope, Taut};
+    use crate::{parse_motion, read_motions, trail::Bounds};
+
+    #[test]
+    fn test_apply() {
+        let tests = [
+            ((0, 0), (0, 0), "R 1", (1, 0), (0, 0)),
+            ((4, 0), (3, 0), "U 4", (4, 4), (4, 3)),
+            ((4, 4), (4, 3), "L 3", (1, 4), (2, 4)),
+            ((1, 4), (2, 4), "D 1", (1, 3), (2, 4)),
+            ((1, 3), (2, 4), "R 4", (5, 3), (4, 3)),
+            ((5, 3), (4, 3), "D 1", (5, 2), (4, 3)),
+            ((5, 2), (4, 3), "L 5", (0, 2), (1, 2)),
+            ((0, 2), (1, 2), "R 2", (2, 2), (1, 2)),
+        ];
+
+        for test in tests {
+            let (dir, n) = parse_motion(test.2).unwrap();
+            let bounds = Bounds::of_motions(test.0, &[(dir, n)]);
+            let mut rope = Rope::from_knots(vec![test.0, test.1], Taut, bounds);
+            rope.apply(&dir, n);
+            assert_eq!(rope.knots(), [test.3, test.4]);
+        }
+    }
+
+    #[test]
+    fn test_visited_per_knot() {
+        let motions = read_motions("test_input2.txt");
+        let mut rope = Rope::for_motions(10, Taut, &motions).tracking(0..10);
+        for &(dir, n) in &motions {
+            rope.apply(&dir, n);
+        }
+
+        assert_eq!(rope.visited(9).len(), 36);
+        // the second knot is where the tail of part 1 would be
+        let mut short = Rope::for_motions(2, Taut, &motions).tracking_tail();
+        for &(dir, n) in &motions {
+            short.apply(&dir, n);
+        }
+        assert_eq!(rope.visited(1), short.visited(1));
+        // every knot sees at least as much as the ones behind it
+        for i in 1..10 {
+            assert!(rope.visited(i - 1).len() >= rope.visited(i).len());
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let tests = [
+            ((2, 0), (0, 0), (1, 0), (1, 0), (0, 0)),
+            ((2, 1), (0, 0), (1, 1), (1, 0), (0, 0)),
+            ((1, 2), (0, 0), (1, 1), (0, 1), (0, 0)),
+            ((2, 2), (0, 0), (1, 1), (1, 0), (0, 0)),
+            ((3, -1), (0, 0), (1, -1), (1, 0), (1, -1)),
+            ((1, 1), (0, 0), (0, 0), (0, 0), (0, 0)),
+        ];
+
+        for (leader, knot, taut, orthogonal, elastic) in tests {
+            assert_eq!(Taut.follow(leader, knot), taut);
+            assert_eq!(Orthogonal.follow(leader, knot), orthogonal);
+            assert_eq!(Elastic(2).follow(leader, knot), elastic);
+            assert_eq!(Elastic(1).follow(leader, knot), taut);
+        }
+    }
+
+    /// the same motions one step at a time
+    fn stepwise<R: FollowRule>(rope: &mut Rope<R>, motions: &[(crate::Direction, u32)]) {
+        for (dir, n) in motions {
+            for _ in 0..*n {
+                rope.step(dir);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        use crate::Direction::*;
+        let motions = [
+            (Right, 3000),
+            (Up, 3),
+            (Left, 5),
+            (UpLeft, 2000),
+            (Down, 1),
+            (DownRight, 7),
+            (Up, 4000),
+            (Left, 2),
+        ];
+
+        for len in [1, 2, 10] {
+            let mut fast = Rope::for_motions(len, Taut, &motions).tracking(0..len);
+            let mut slow = Rope::for_motions(len, Taut, &motions).tracking(0..len);
+            for (dir, n) in &motions {
+                fast.apply(dir, *n);
+            }
+            stepwise(&mut slow, &motions);
+
+            assert_eq!(fast.knots(), slow.knots());
+            for i in 0..len {
+                assert_eq!(fast.visited(i), slow.visited(i));
+            }
+        }
+
+        // knots spaced out by more than one take longer to line up
+        let mut fast = Rope::for_motions(4, Elastic(3), &motions).tracking_tail();
+        let mut slow = Rope::for_motions(4, Elastic(3), &motions).tracking_tail();
+        for (dir, n) in &motions {
+            fast.apply(dir, *n);
+        }
+        stepwise(&mut slow, &motions);
+        assert_eq!(fast.knots(), slow.knots());
+        assert_eq!(fast.visited(3), slow.visited(3));
+    }
+
+    #[test]
+    fn test_long_motions() {
+        use crate::Direction::*;
+        // a bit for every cell of the box would be over a gigabyte
+        let motions = [(Right, 100_000), (Up, 100_000)];
+        let mut rope = Rope::for_motions(2, Taut, &motions).tracking_tail();
+        for (dir, n) in &motions {
+            rope.apply(dir, *n);
+        }
+        assert!(!rope.visited(1).is_dense());
+        assert_eq!(rope.knots(), [(100_000, 100_000), (100_000, 99_999)]);
+        assert_eq!(rope.visited(1).len(), 199_999);
+    }
+
+    #[test]
+    fn test_furthest_motions() {
+        use crate::Direction::*;
+        let most = i32::MAX as u32;
+        let motions = [(Right, most), (Left, most), (Left, most)];
+        let mut rope = Rope::for_motions(3, Taut, &motions);
+        for (dir, n) in &motions {
+            rope.apply(dir, *n);
+        }
+        assert_eq!(
+            rope.knots(),
+            [(-i32::MAX, 0), (1 - i32::MAX, 0), (2 - i32::MAX, 0)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "knot 0 isn't tracked")]
+    fn test_untracked() {
+        Rope::new(2, Taut, Bounds::fit([]))
+            .tracking_tail()
+            .visited(0);
+    }
+
+    #[test]
+    fn test_elastic_rope() {
+        let motions = [(crate::Direction::Right, 10)];
+        let mut rope = Rope::for_motions(3, Elastic(2), &motions).tracking_tail();
+        rope.apply(&motions[0].0, motions[0].1);
+
+        // each knot trails two behind the one in front
+        assert_eq!(rope.knots(), [(10, 0), (8, 0), (6, 0)]);
+        assert_eq!(rope.visited(2).len(), 7);
+    }
+}
@@ -0,0 +1,272 @@
+//! What every knot did over a whole run, not just where it's been.
+//!
+//! Distances are in steps, a diagonal step counting as one like it does for
+//! the knots themselves. A knot visits a cell each time it arrives there,
+//! the start included, so standing still doesn't count.
+
+use crate::{
+    rope::{FollowRule, Rope},
+    trail::Bounds,
+    Coord, Direction,
+};
+use std::{collections::HashMap, fmt::Write};
+
+/// the most pixels a heatmap can have, 64 MiB of them
+const MAX_HEATMAP_PIXELS: usize = 1 << 26;
+
+pub struct KnotStats {
+    /// how many times the knot arrived in each cell it's been in
+    visits: HashMap<Coord, u32>,
+    /// how many cells it has been in
+    pub cells: usize,
+    /// furthest from the origin it ever got
+    pub max_distance: u32,
+    pub travelled: u64,
+    /// how many steps of the head it didn't move for
+    pub still: u64,
+}
+
+pub struct Replay {
+    pub knots: Vec<KnotStats>,
+}
+
+fn distance(cell: Coord) -> u32 {
+    cell.0.unsigned_abs().max(cell.1.unsigned_abs())
+}
+
+/// Runs `motions` one step at a time on `rope`, keeping track of every
+/// knot. The rope doesn't need to track any knots itself.
+pub fn replay<R: FollowRule>(motions: &[(Direction, u32)], rope: Rope<R>) -> Replay {
+    let mut rope = rope;
+    let mut knots: Vec<KnotStats> = rope
+        .knots()
+        .iter()
+        .map(|&knot| KnotStats {
+            visits: HashMap::from([(knot, 1)]),
+            cells: 1,
+            max_distance: distance(knot),
+            travelled: 0,
+            still: 0,
+        })
+        .collect();
+
+    let mut before = rope.knots().to_vec();
+    for (dir, n) in motions {
+        for _ in 0..*n {
+            rope.step(dir);
+            for ((stats, &knot), was) in knots.iter_mut().zip(rope.knots()).zip(&before) {
+                if knot == *was {
+                    stats.still += 1;
+                    continue;
+                }
+                let visits = stats.visits.entry(knot).or_insert(0);
+                stats.cells += (*visits == 0) as usize;
+                *visits = visits.saturating_add(1);
+                stats.max_distance = stats.max_distance.max(distance(knot));
+                stats.travelled += 1;
+            }
+            before.copy_from_slice(rope.knots());
+        }
+    }
+
+    Replay { knots }
+}
+
+impl Replay {
+    /// the cells knot `i` has been in with how often, bottom row first
+    /// and left to right
+    pub fn visited(&self, i: usize) -> Vec<(Coord, u32)> {
+        let mut cells: Vec<_> = self.knots[i].visits.iter().map(|(&c, &n)| (c, n)).collect();
+        cells.sort_unstable_by_key(|&((x, y), _)| (y, x));
+        cells
+    }
+
+    /// one line per knot, head first, under a header
+    pub fn to_csv(&self) -> String {
+        let mut out = "knot,cells,max_distance,travelled,still\n".to_string();
+        for (i, stats) in self.knots.iter().enumerate() {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                i, stats.cells, stats.max_distance, stats.travelled, stats.still
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// every cell a knot has been in and how often, bottom row first
+    pub fn visits_to_csv(&self) -> String {
+        let mut out = "knot,x,y,visits\n".to_string();
+        for i in 0..self.knots.len() {
+            for ((x, y), visits) in self.visited(i) {
+                writeln!(out, "{},{},{},{}", i, x, y, visits).unwrap();
+            }
+        }
+        out
+    }
+
+    /// the same as `to_csv`, as an array of objects
+    pub fn to_json(&self) -> String {
+        let objects: Vec<String> = self
+            .knots
+            .iter()
+            .enumerate()
+            .map(|(i, stats)| {
+                format!(
+                    "  {{\"knot\": {}, \"cells\": {}, \"max_distance\": {}, \"travelled\": {}, \"still\": {}}}",
+                    i, stats.cells, stats.max_distance, stats.travelled, stats.still
+                )
+            })
+            .collect();
+        format!("[\n{}\n]\n", objects.join(",\n"))
+    }
+
+    /// Binary greyscale image of how often knot `i` arrived in each cell,
+    /// the busiest one white, cropped to the cells it's been in. Rows go
+    /// from the top down like `render`. Fails when that's more than
+    /// `MAX_HEATMAP_PIXELS`.
+    pub fn heatmap(&self, i: usize) -> Result<Vec<u8>, String> {
+        let visits = &self.knots[i].visits;
+        let mut cells = visits.keys().copied();
+        let first = cells.next().expect("every knot has been somewhere");
+        let mut crop = Bounds {
+            min: first,
+            max: first,
+        };
+        for cell in cells {
+            crop.include(cell);
+        }
+
+        let (w, h) = (crop.width(), crop.height());
+        let too_big = match w.checked_mul(h) {
+            Some(pixels) => pixels > MAX_HEATMAP_PIXELS,
+            None => true,
+        };
+        if too_big {
+            return Err(format!(
+                "knot {} went over {}x{} cells, too many for a heatmap",
+                i, w, h
+            ));
+        }
+        let max = visits.values().copied().max().unwrap_or(0).max(1) as u64;
+
+        let mut pixels = vec![0; w * h];
+        for (&(x, y), &n) in visits {
+            let (x, y) = ((x - crop.min.0) as usize, (crop.max.1 - y) as usize);
+            pixels[y * w + x] = (n as u64 * 255 / max) as u8;
+        }
+        let mut out = format!("P5\n{} {}\n255\n", w, h).into_bytes();
+        out.extend(pixels);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay, Replay};
+    use crate::{
+        read_motions,
+        rope::{Rope, Taut},
+        solve2,
+        trail::Bounds,
+        Coord,
+        Direction::*,
+    };
+
+    /// how many times knot `i` arrived in `cell`
+    fn visits(replay: &Replay, i: usize, cell: Coord) -> u32 {
+        let visited = replay.visited(i);
+        visited
+            .iter()
+            .find(|&&(visited, _)| visited == cell)
+            .map_or(0, |&(_, n)| n)
+    }
+
+    #[test]
+    fn test_replay() {
+        let motions = read_motions("test_input.txt");
+        let replay = replay(&motions, Rope::for_motions(2, Taut, &motions));
+        let (head, tail) = (&replay.knots[0], &replay.knots[1]);
+
+        assert_eq!(head.travelled, 24);
+        assert_eq!(head.still, 0);
+        assert_eq!(head.max_distance, 5);
+        assert_eq!(tail.cells, solve2("test_input.txt", 2) as usize);
+        assert_eq!(tail.max_distance, 4);
+        // the head goes through on L 5 and comes back on R 2
+        assert_eq!(visits(&replay, 0, (2, 2)), 2);
+        assert_eq!(visits(&replay, 0, (-1, 0)), 0);
+    }
+
+    #[test]
+    fn test_replay_adds_up() {
+        let motions = read_motions("test_input2.txt");
+        let steps: u64 = motions.iter().map(|&(_, n)| n as u64).sum();
+        let mut rope = Rope::for_motions(10, Taut, &motions).tracking(0..10);
+        let replay = super::replay(&motions, Rope::for_motions(10, Taut, &motions));
+        for &(dir, n) in &motions {
+            rope.apply(&dir, n);
+        }
+
+        for (i, stats) in replay.knots.iter().enumerate() {
+            assert_eq!(stats.travelled + stats.still, steps);
+            assert_eq!(stats.cells, rope.visited(i).len());
+            let arrivals: u64 = rope
+                .visited(i)
+                .iter()
+                .map(|cell| visits(&replay, i, cell) as u64)
+                .sum();
+            assert_eq!(arrivals, stats.travelled + 1);
+        }
+        assert_eq!(replay.knots[9].cells, 36);
+    }
+
+    #[test]
+    fn test_replay_anywhere() {
+        // the head starts away from the origin
+        let rope = Rope::from_knots(vec![(5, 5), (5, 4)], Taut, Bounds::fit([]));
+        let replay = replay(&[(Right, 2)], rope);
+        assert_eq!(visits(&replay, 1, (5, 4)), 1);
+        assert_eq!(visits(&replay, 1, (6, 5)), 1);
+        // only the cells the tail has been in
+        let mut expected = b"P5\n2 2\n255\n".to_vec();
+        expected.extend([0, 255, 255, 0]);
+        assert_eq!(replay.heatmap(1), Ok(expected));
+
+        // far more cells in the box than ever visited
+        let motions = [(Right, 100_000), (Up, 100_000)];
+        let replay = super::replay(&motions, Rope::for_motions(2, Taut, &motions));
+        assert_eq!(replay.knots[1].cells, 199_999);
+        assert_eq!(visits(&replay, 0, (100_000, 100_000)), 1);
+        assert_eq!(
+            replay.heatmap(1),
+            Err("knot 1 went over 100001x100000 cells, too many for a heatmap".to_string())
+        );
+    }
+
+    #[test]
+    fn test_exports() {
+        let motions = [(Right, 2)];
+        let replay = replay(&motions, Rope::for_motions(2, Taut, &motions));
+
+        assert_eq!(
+            replay.to_csv(),
+            "knot,cells,max_distance,travelled,still\n0,3,2,2,0\n1,2,1,1,1\n"
+        );
+        assert_eq!(
+            replay.to_json(),
+            "[\n  {\"knot\": 0, \"cells\": 3, \"max_distance\": 2, \"travelled\": 2, \"still\": 0},\n  \
+             {\"knot\": 1, \"cells\": 2, \"max_distance\": 1, \"travelled\": 1, \"still\": 1}\n]\n"
+        );
+
+        assert_eq!(
+            replay.visits_to_csv(),
+            "knot,x,y,visits\n0,0,0,1\n0,1,0,1\n0,2,0,1\n1,0,0,1\n1,1,0,1\n"
+        );
+
+        let mut expected = b"P5\n2 1\n255\n".to_vec();
+        expected.extend([255, 255]);
+        assert_eq!(replay.heatmap(1), Ok(expected));
+    }
+}
@@ -0,0 +1,364 @@
+//! Remembering which cells a knot has been in, one bit per cell of a box
+//! known up front.
+//!
+//! Knots only ever step towards the knot in front of them, so no knot
+//! leaves the box the head stays in. That box comes from adding up the
+//! motions before running any of them. A few long motions can make it
+//! far too big for a bit per cell though, then the cells are hashed.
+
+use crate::{Coord, Direction};
+use std::collections::HashSet;
+
+/// the most cells a box can have to get a bit each, 16 MiB of them
+const DENSE_CELLS: usize = 1 << 27;
+
+/// `count` steps of `step` from `from`. Panics when that's further than a
+/// coordinate goes.
+pub fn line_end(from: Coord, step: Coord, count: u32) -> Coord {
+    (
+        axis_end(from.0, step.0, count),
+        axis_end(from.1, step.1, count),
+    )
+}
+
+/// the same as `line_end` along a single axis
+pub fn axis_end(from: i32, step: i32, count: u32) -> i32 {
+    i32::try_from(from as i64 + step as i64 * count as i64).unwrap_or_else(|_| {
+        panic!(
+            "{} steps from {} go further than a coordinate can",
+            count, from
+        )
+    })
+}
+
+/// a box of cells, `min` and `max` included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Coord,
+    pub max: Coord,
+}
+
+impl Bounds {
+    /// the smallest box holding the start and all of `cells`
+    pub fn fit(cells: impl IntoIterator<Item = Coord>) -> Bounds {
+        let mut bounds = Bounds {
+            min: (0, 0),
+            max: (0, 0),
+        };
+        for cell in cells {
+            bounds.include(cell);
+        }
+        bounds
+    }
+
+    /// every cell the head goes through when it starts at `start` and
+    /// follows `motions`
+    pub fn of_motions(start: Coord, motions: &[(Direction, u32)]) -> Bounds {
+        let mut bounds = Bounds::fit([start]);
+        let mut head = start;
+        for (dir, n) in motions {
+            // straight lines, so the ends are enough
+            head = line_end(head, dir.vector(), *n);
+            bounds.include(head);
+        }
+        bounds
+    }
+
+    pub fn include(&mut self, cell: Coord) {
+        self.min = (self.min.0.min(cell.0), self.min.1.min(cell.1));
+        self.max = (self.max.0.max(cell.0), self.max.1.max(cell.1));
+    }
+
+    pub fn contains(&self, cell: Coord) -> bool {
+        (self.min.0..=self.max.0).contains(&cell.0) && (self.min.1..=self.max.1).contains(&cell.1)
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.0 as i64 - self.min.0 as i64) as usize + 1
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.1 as i64 - self.min.1 as i64) as usize + 1
+    }
+}
+
+/// `cell` counted from the bottom left corner of `bounds`, row by row
+fn index(bounds: &Bounds, cell: Coord) -> usize {
+    let (x, y) = (
+        cell.0 as i64 - bounds.min.0 as i64,
+        cell.1 as i64 - bounds.min.1 as i64,
+    );
+    y as usize * bounds.width() + x as usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cells {
+    /// a bit per cell of the bounds, bottom row first
+    Dense(Vec<u64>),
+    Sparse(HashSet<Coord>),
+}
+
+/// a set of cells inside some `Bounds`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trail {
+    bounds: Bounds,
+    cells: Cells,
+    len: usize,
+}
+
+impl Trail {
+    pub fn new(bounds: Bounds) -> Trail {
+        let cells = match bounds.width().checked_mul(bounds.height()) {
+            Some(cells) if cells <= DENSE_CELLS => Cells::Dense(vec![0; cells.div_ceil(64)]),
+            _ => Cells::Sparse(HashSet::new()),
+        };
+        Trail {
+            bounds,
+            cells,
+            len: 0,
+        }
+    }
+
+    /// whether there's a bit per cell rather than a hash set
+    pub fn is_dense(&self) -> bool {
+        matches!(self.cells, Cells::Dense(_))
+    }
+
+    fn check(&self, cell: Coord) {
+        assert!(
+            self.bounds.contains(cell),
+            "({}, {}) is outside the trail's bounds",
+            cell.0,
+            cell.1
+        );
+    }
+
+    /// where the bit for `cell` is in a dense trail
+    fn index(&self, cell: Coord) -> usize {
+        self.check(cell);
+        index(&self.bounds, cell)
+    }
+
+    /// adds `cell`, telling whether it's new. Panics outside the bounds.
+    pub fn insert(&mut self, cell: Coord) -> bool {
+        self.check(cell);
+        let new = match &mut self.cells {
+            Cells::Dense(bits) => {
+                let i = index(&self.bounds, cell);
+                let (word, bit) = (i / 64, 1 << (i % 64));
+                let new = bits[word] & bit == 0;
+                bits[word] |= bit;
+                new
+            }
+            Cells::Sparse(cells) => cells.insert(cell),
+        };
+        self.len += new as usize;
+        new
+    }
+
+    /// Adds the `count` cells after `from` going by `step`, leaving `from`
+    /// itself out. In a dense trail rows are filled a word at a time and
+    /// other lines go from bit to bit without working out every cell. A
+    /// sparse trail has no such shortcut, it hashes every cell of the line
+    /// one by one, so there a line costs as much as its length.
+    pub fn insert_line(&mut self, from: Coord, step: Coord, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let first = line_end(from, step, 1);
+        let last = line_end(from, step, count);
+        if !self.is_dense() {
+            let mut cell = from;
+            for _ in 0..count {
+                cell = (cell.0 + step.0, cell.1 + step.1);
+                self.insert(cell);
+            }
+            return;
+        }
+
+        // a straight line is inside the box if both its ends are
+        let (first, last) = (self.index(first), self.index(last));
+        let Cells::Dense(words) = &mut self.cells else {
+            unreachable!("sparse trails go cell by cell");
+        };
+        if step.1 != 0 || step.0.abs() != 1 {
+            let stride = step.1 as isize * self.bounds.width() as isize + step.0 as isize;
+            let mut i = first;
+            for _ in 0..count {
+                let (word, bit) = (i / 64, 1 << (i % 64));
+                self.len += (words[word] & bit == 0) as usize;
+                words[word] |= bit;
+                i = i.wrapping_add_signed(stride);
+            }
+            return;
+        }
+
+        // along a row the bits are next to each other
+        let (first, last) = (first.min(last), first.max(last));
+        let mut i = first;
+        while i <= last {
+            let (word, bit) = (i / 64, i % 64);
+            let bits = (last - i + 1).min(64 - bit);
+            let mask = if bits == 64 {
+                u64::MAX
+            } else {
+                ((1u64 << bits) - 1) << bit
+            };
+            self.len += (mask & !words[word]).count_ones() as usize;
+            words[word] |= mask;
+            i += bits;
+        }
+    }
+
+    /// how many different cells are in the trail
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// every cell, bottom row first and left to right
+    pub fn iter(&self) -> impl Iterator<Item = Coord> + '_ {
+        let (words, sparse) = match &self.cells {
+            Cells::Dense(words) => (&words[..], vec![]),
+            Cells::Sparse(cells) => {
+                let mut cells: Vec<Coord> = cells.iter().copied().collect();
+                cells.sort_unstable_by_key(|&(x, y)| (y, x));
+                (&[][..], cells)
+            }
+        };
+        let width = self.bounds.width();
+        let dense = words.iter().enumerate().flat_map(move |(word, &bits)| {
+            (0..64)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| {
+                    let i = word * 64 + bit;
+                    (
+                        self.bounds.min.0 + (i % width) as i32,
+                        self.bounds.min.1 + (i / width) as i32,
+                    )
+                })
+        });
+        dense.chain(sparse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{line_end, Bounds, Trail};
+    use crate::{read_motions, Direction::*};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_bounds_of_motions() {
+        let motions = read_motions("test_input2.txt");
+        let bounds = Bounds::of_motions((0, 0), &motions);
+        assert_eq!(
+            bounds,
+            Bounds {
+                min: (-11, -5),
+                max: (14, 15)
+            }
+        );
+
+        let bounds = Bounds::of_motions((3, 3), &[(DownLeft, 5), (Right, 1)]);
+        assert_eq!(
+            bounds,
+            Bounds {
+                min: (-2, -2),
+                max: (3, 3)
+            }
+        );
+    }
+
+    #[test]
+    fn test_trail() {
+        let mut trail = Trail::new(Bounds::fit([(-3, -2), (70, 4)]));
+        let cells = [(0, 0), (-3, -2), (70, 4), (5, 1), (0, 0), (69, -2)];
+        let mut expected = HashSet::new();
+
+        for cell in cells {
+            assert_eq!(trail.insert(cell), expected.insert(cell));
+        }
+        assert_eq!(trail.len(), 5);
+        assert_eq!(trail.iter().collect::<HashSet<_>>(), expected);
+        assert_eq!(trail.iter().next(), Some((-3, -2)));
+    }
+
+    #[test]
+    fn test_insert_line() {
+        let bounds = Bounds::fit([(-100, -3), (200, 3)]);
+        let lines = [
+            ((-100, 0), (1, 0), 300),
+            ((50, 1), (-1, 0), 150),
+            ((0, -3), (0, 1), 6),
+            ((-5, -3), (1, 1), 6),
+            ((7, 2), (1, 0), 0),
+            ((-60, 0), (1, 0), 100),
+            ((150, 3), (0, -1), 6),
+            ((190, -3), (-1, 1), 6),
+        ];
+
+        let mut trail = Trail::new(bounds);
+        let mut expected = Trail::new(bounds);
+        for (from, step, count) in lines {
+            trail.insert_line(from, step, count);
+            let mut cell = from;
+            for _ in 0..count {
+                cell = (cell.0 + step.0, cell.1 + step.1);
+                expected.insert(cell);
+            }
+            assert_eq!(trail, expected);
+        }
+        // the columns and the diagonals cross the rows and each other
+        assert_eq!(trail.len(), 300 + 150 + 4 + 3 + 5 + 5);
+    }
+
+    #[test]
+    fn test_sparse() {
+        // far too many cells for a bit each
+        let huge = Bounds::fit([(-100, -3), (1 << 30, 1 << 20)]);
+        let small = Bounds::fit([(-100, -3), (200, 3)]);
+        let mut sparse = Trail::new(huge);
+        let mut dense = Trail::new(small);
+        assert!(!sparse.is_dense() && dense.is_dense());
+
+        for (from, step, count) in [
+            ((-100, 0), (1, 0), 300),
+            ((0, -3), (0, 1), 6),
+            ((-5, -3), (1, 1), 6),
+            ((50, 1), (-1, 0), 150),
+        ] {
+            sparse.insert_line(from, step, count);
+            dense.insert_line(from, step, count);
+        }
+        assert_eq!(sparse.insert((7, 3)), dense.insert((7, 3)));
+        assert_eq!(sparse.len(), dense.len());
+        assert!(sparse.iter().eq(dense.iter()));
+    }
+
+    #[test]
+    fn test_furthest_motions() {
+        let most = i32::MAX as u32;
+        let bounds = Bounds::of_motions((0, 0), &[(Right, most), (Left, most), (DownLeft, most)]);
+        assert_eq!(
+            bounds,
+            Bounds {
+                min: (-i32::MAX, -i32::MAX),
+                max: (i32::MAX, 0)
+            }
+        );
+        assert_eq!(bounds.width(), u32::MAX as usize);
+        assert!(!Trail::new(bounds).is_dense());
+    }
+
+    #[test]
+    #[should_panic(expected = "further than a coordinate can")]
+    fn test_too_far() {
+        line_end((1, 0), (1, 0), i32::MAX as u32);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the trail's bounds")]
+    fn test_outside() {
+        Trail::new(Bounds::fit([(1, 1)])).insert((2, 0));
+    }
+}